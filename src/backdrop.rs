@@ -0,0 +1,349 @@
+//! Backdrops to paint transparent pixels of frames over: The background-color
+//! or a background-image, scaled and aligned on the screen like the frames.
+//! Each frame is painted over the part of the image it covers.
+
+use std::sync::Arc;
+
+use crate::decoder;
+use crate::options::Options;
+use crate::position::{ImagePlacement, Resolution};
+use crate::raster;
+use crate::resampling;
+use crate::screens::Screen;
+
+/// Still image to show through transparent pixels of frames.
+pub struct BackgroundImage {
+    /// RGBA-pixels. Transparent pixels show the background-color.
+    raster: Vec<u8>,
+    resolution: Resolution,
+}
+
+impl BackgroundImage {
+    /// Decodes the image-file. Of an animation only the first frame is used.
+    pub fn open(path: &str) -> Result<BackgroundImage, String> {
+        let mut source = decoder::open_frames(path)?;
+        let resolution = source.resolution().clone();
+
+        let frame = source
+            .next()
+            .ok_or_else(|| format!("Background-image {} has no frames", path))?
+            .map_err(|e| format!("Background-image {} is not valid: {}", path, e))?;
+
+        Ok(BackgroundImage {
+            raster: frame.raster,
+            resolution,
+        })
+    }
+}
+
+/// Position of frames on the area of the background-image, which determines
+/// the part of the image shown through their transparent pixels.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct BackdropPosition {
+    /// Resolution of the area, the background-image is placed on.
+    pub area: Resolution,
+    /// Origin of the frames, relative to the area.
+    pub x: i32,
+    pub y: i32,
+}
+
+impl BackdropPosition {
+    /// Position of frames drawn by the placement on the area, both in
+    /// coordinates of the root-window.
+    pub fn of(placement: &ImagePlacement, area: &Screen) -> BackdropPosition {
+        BackdropPosition {
+            area: Resolution::new(area.width, area.height),
+            x: placement.dest_x - placement.src_x - area.x_org,
+            y: placement.dest_y - placement.src_y - area.y_org,
+        }
+    }
+}
+
+/// Background-image placed on an area, as painted around the frames.
+pub struct PlacedBackground {
+    /// Opaque RGBA-pixels of the whole area. Parts not covered by the image
+    /// are of the background-color.
+    pub raster: Vec<u8>,
+    pub resolution: Resolution,
+    /// Parts of the area covered by the image, relative to its origin.
+    pub placements: Vec<ImagePlacement>,
+}
+
+/// Places the image on an area of given resolution. The image is scaled like
+/// the frames, as if the area was its screen, then aligned and moved by the
+/// offset. Tiling-modes repeat the image in its own size.
+pub fn place_background(
+    image: &BackgroundImage,
+    resolution: &Resolution,
+    background_rgba: &[u8; 4],
+    options: Arc<Options>,
+) -> PlacedBackground {
+    let is_tiled = options.scaling.is_tiled();
+
+    let fitted_resolution = if is_tiled {
+        image.resolution.clone()
+    } else {
+        image.resolution.fit_to_screen(resolution, &options.scaling)
+    };
+
+    let fitted = if options.linear_light {
+        let scaled = resampling::resize_linear_raster(
+            raster::premultiply_linear(&image.raster),
+            &image.resolution,
+            &fitted_resolution,
+            options.clone(),
+        );
+
+        raster::composite_linear_over(&scaled, background_rgba)
+    } else {
+        let scaled = resampling::resize_raster(
+            raster::premultiply(&image.raster),
+            &image.resolution,
+            &fitted_resolution,
+            options.clone(),
+        );
+
+        raster::composite_over(&scaled, background_rgba)
+    };
+
+    let area = screen_of(resolution);
+
+    let placements = if is_tiled {
+        fitted_resolution.tile_on_screen(&area, options.alignment, options.offset)
+    } else {
+        vec![fitted_resolution.position_on_screen(&area, options.alignment, options.offset)]
+    };
+
+    let mut raster = fill(background_rgba, resolution);
+
+    for placement in &placements {
+        raster::paint(
+            &fitted,
+            &fitted_resolution,
+            &mut raster,
+            resolution,
+            placement,
+        );
+    }
+
+    PlacedBackground {
+        raster,
+        resolution: resolution.clone(),
+        placements,
+    }
+}
+
+/// Renders the opaque RGBA-backdrop for frames in given resolution. Without an
+/// image, this is a single pixel of the background-color.
+///
+/// Otherwise it is the part of the placed background-image, which the frames
+/// cover at their position. Parts of frames beyond the area show the
+/// background-color.
+pub fn render_backdrop(
+    image: Option<&BackgroundImage>,
+    position: Option<&BackdropPosition>,
+    resolution: &Resolution,
+    background_rgba: &[u8; 4],
+    options: Arc<Options>,
+) -> Vec<u8> {
+    let (image, position) = match (image, position) {
+        (Some(image), Some(position)) => (image, position),
+        _ => return background_rgba.to_vec(),
+    };
+
+    let background = place_background(image, &position.area, background_rgba, options);
+
+    let mut backdrop = fill(background_rgba, resolution);
+
+    let covered = ImagePlacement::new(
+        0,
+        0,
+        -position.x,
+        -position.y,
+        position.area.width,
+        position.area.height,
+    )
+    .clip_to_screen(&screen_of(resolution));
+
+    if let Some(covered) = covered {
+        raster::paint(
+            &background.raster,
+            &background.resolution,
+            &mut backdrop,
+            resolution,
+            &covered,
+        );
+    }
+
+    backdrop
+}
+
+/// Raster of given resolution in a single color.
+fn fill(rgba: &[u8; 4], resolution: &Resolution) -> Vec<u8> {
+    let size = resolution.width as usize * resolution.height as usize * 4;
+    rgba.iter().copied().cycle().take(size).collect()
+}
+
+/// Screen at the origin, to place images on a raster of given resolution.
+fn screen_of(resolution: &Resolution) -> Screen {
+    Screen {
+        screen_number: 0,
+        x_org: 0,
+        y_org: 0,
+        width: resolution.width,
+        height: resolution.height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{place_background, render_backdrop, BackdropPosition, BackgroundImage};
+    use crate::options::Options;
+    use crate::position::Resolution;
+    use std::sync::Arc;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    #[test]
+    fn when_there_is_no_image_then_backdrop_is_background_color() {
+        let actual = render_backdrop(
+            None,
+            Some(&_position(4, 4, 0, 0)),
+            &Resolution::new(4, 4),
+            &BLACK,
+            _options(vec![]),
+        );
+
+        assert_eq!(actual, BLACK.to_vec());
+    }
+
+    #[test]
+    fn when_image_is_smaller_then_align_it_on_background_color() {
+        let actual = place_background(
+            &_image(),
+            &Resolution::new(3, 1),
+            &BLACK,
+            _options(vec!["-a", "RIGHT"]),
+        );
+
+        assert_eq!(
+            actual.raster,
+            vec![0, 0, 0, 255, 0, 0, 0, 255, 9, 9, 9, 255]
+        );
+        assert_eq!(actual.placements.len(), 1);
+    }
+
+    #[test]
+    fn when_offset_is_given_then_move_image_by_it() {
+        let actual = place_background(
+            &_image(),
+            &Resolution::new(3, 1),
+            &BLACK,
+            _options(vec!["-a", "LEFT", "--offset", "1,0"]),
+        );
+
+        assert_eq!(
+            actual.raster,
+            vec![0, 0, 0, 255, 9, 9, 9, 255, 0, 0, 0, 255]
+        );
+    }
+
+    #[test]
+    fn when_image_is_tiled_then_repeat_it_in_own_size() {
+        let actual = place_background(
+            &_image(),
+            &Resolution::new(2, 1),
+            &BLACK,
+            _options(vec!["-s", "TILE", "-a", "LEFT"]),
+        );
+
+        assert_eq!(actual.raster, vec![9, 9, 9, 255, 9, 9, 9, 255]);
+        assert_eq!(actual.placements.len(), 2);
+    }
+
+    #[test]
+    fn when_image_is_stretched_then_cover_area() {
+        let actual = place_background(
+            &_image(),
+            &Resolution::new(2, 3),
+            &BLACK,
+            _options(vec!["-s", "STRETCH"]),
+        );
+
+        assert_eq!(actual.raster.len(), 2 * 3 * 4);
+        assert!(actual
+            .raster
+            .chunks_exact(4)
+            .all(|pixel| pixel == [9, 9, 9, 255]));
+    }
+
+    #[test]
+    fn when_image_extends_past_frame_then_backdrop_is_covered_part() {
+        let image = BackgroundImage {
+            raster: vec![1, 1, 1, 255, 2, 2, 2, 255, 3, 3, 3, 255],
+            resolution: Resolution::new(3, 1),
+        };
+        let options = _options(vec!["-s", "NONE", "-a", "LEFT"]);
+
+        let actual = render_backdrop(
+            Some(&image),
+            Some(&_position(3, 1, 1, 0)),
+            &Resolution::new(1, 1),
+            &BLACK,
+            options.clone(),
+        );
+
+        assert_eq!(actual, vec![2, 2, 2, 255]);
+
+        let actual = render_backdrop(
+            Some(&image),
+            Some(&_position(3, 1, 2, 0)),
+            &Resolution::new(2, 1),
+            &BLACK,
+            options,
+        );
+
+        assert_eq!(
+            actual,
+            vec![3, 3, 3, 255, 0, 0, 0, 255],
+            "Beyond the area is background-color"
+        );
+    }
+
+    #[test]
+    fn when_image_file_is_opened_then_decode_first_frame() {
+        let image = BackgroundImage::open("tests/samples/sample-disposal-keep.gif").unwrap();
+
+        assert_eq!(
+            image.raster.len(),
+            (image.resolution.width * image.resolution.height * 4) as usize
+        );
+    }
+
+    #[test]
+    fn when_image_file_does_not_exist_then_fail() {
+        assert!(BackgroundImage::open("tests/samples/no-such-file.png").is_err());
+    }
+
+    /// Single opaque pixel.
+    fn _image() -> BackgroundImage {
+        BackgroundImage {
+            raster: vec![9, 9, 9, 255],
+            resolution: Resolution::new(1, 1),
+        }
+    }
+
+    fn _position(area_width: u32, area_height: u32, x: i32, y: i32) -> BackdropPosition {
+        BackdropPosition {
+            area: Resolution::new(area_width, area_height),
+            x,
+            y,
+        }
+    }
+
+    fn _options(custom_params: Vec<&str>) -> Arc<Options> {
+        Arc::new(Options::_from_params(
+            [vec!["xgifwallpaper"], custom_params, vec!["a.gif"]].concat(),
+        ))
+    }
+}
@@ -0,0 +1,506 @@
+//! Persistent cache of rendered frames on disk, to skip decoding and scaling
+//! of the same GIF on the next start.
+//!
+//! Frames are cached per target-resolution and position on the background-
+//! image. The file-name is derived from the content of the GIF and all options
+//! affecting the rendered pixels or delays.
+//! Once the cache grows beyond its limit, the least recently used files are
+//! removed.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::options::Options;
+use crate::position::Resolution;
+use crate::sequence;
+use crate::{FrameSetKey, PixelFormat, RenderedFrame};
+
+const MAGIC: &[u8; 8] = b"XGWCACHE";
+const FORMAT_VERSION: u32 = 2;
+
+/// Size in bytes, up to which the cache grows.
+const MAX_CACHE_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Cache-entries of a single GIF, rendered with specific options.
+pub struct Cache {
+    dir: PathBuf,
+    key: u64,
+}
+
+impl Cache {
+    /// Opens the cache for the file and rendering-options given.
+    pub fn open(path: &str, options: &Options, format: &PixelFormat) -> Result<Cache, String> {
+        let dir = cache_dir().ok_or("Neither XDG_CACHE_HOME nor HOME is set")?;
+
+        // An image-sequence is hashed over the content of all its files
+        let mut content_hash = FNV_OFFSET;
+
+        for path in sequence::input_files(path)? {
+            content_hash = hash_file(content_hash, &path)?;
+        }
+
+        if let Some(path) = &options.background_image {
+            content_hash = hash_file(content_hash, Path::new(path))?;
+        }
+
+        Ok(Cache {
+            dir,
+            key: cache_key(content_hash, options, format),
+        })
+    }
+
+    /// Number of frames cached for given key, if any.
+    pub fn frame_count(&self, key: &FrameSetKey) -> Option<usize> {
+        let mut reader = BufReader::new(File::open(self.path(key)).ok()?);
+        read_header(&mut reader, &key.resolution).ok()
+    }
+
+    /// Loads cached frames of given key.
+    pub fn load(&self, key: &FrameSetKey) -> Option<Vec<RenderedFrame>> {
+        let resolution = &key.resolution;
+        let file = File::open(self.path(key)).ok()?;
+
+        // Marks the file as recently used, to be pruned last
+        let _ = file.set_modified(SystemTime::now());
+
+        let mut reader = BufReader::new(file);
+        let frame_count = read_header(&mut reader, resolution).ok()?;
+        let byte_size = resolution.width as usize * resolution.height as usize * 4;
+
+        let mut frames: Vec<RenderedFrame> = Vec::with_capacity(frame_count);
+
+        for _ in 0..frame_count {
+            let delay = read_u32(&mut reader).ok()?;
+            let mut raster = vec![0; byte_size];
+            reader.read_exact(&mut raster).ok()?;
+
+            frames.push(RenderedFrame {
+                delay: Duration::from_millis(delay as u64),
+                raster,
+            });
+        }
+
+        Some(frames)
+    }
+
+    /// Writes the rendered frames to the cache. Prunes the
+    /// least recently used files afterwards, if the cache grew too large.
+    pub fn store(&self, key: &FrameSetKey, frames: &[RenderedFrame]) -> Result<(), String> {
+        let resolution = &key.resolution;
+        let path = self.path(key);
+        let tmp_path = path.with_extension("tmp");
+
+        let write = || -> std::io::Result<()> {
+            fs::create_dir_all(&self.dir)?;
+
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            write_header(&mut writer, resolution, frames.len())?;
+
+            for frame in frames {
+                writer.write_all(&(frame.delay.as_millis() as u32).to_le_bytes())?;
+                writer.write_all(&frame.raster)?;
+            }
+
+            writer.flush()?;
+
+            // Rename only complete files, to never read a partial one
+            fs::rename(&tmp_path, &path)
+        };
+
+        write().map_err(|e| {
+            let _ = fs::remove_file(&tmp_path);
+            format!("Unable to write cache-file {}: {}", path.display(), e)
+        })?;
+
+        prune(&self.dir, MAX_CACHE_SIZE, &path)
+            .map_err(|e| format!("Unable to prune cache {}: {}", self.dir.display(), e))
+    }
+
+    fn path(&self, key: &FrameSetKey) -> PathBuf {
+        let backdrop = key.backdrop.as_ref().map_or(String::new(), |position| {
+            format!(
+                "-{}x{}{:+}{:+}",
+                position.area.width, position.area.height, position.x, position.y
+            )
+        });
+
+        self.dir.join(format!(
+            "{:016x}-{}x{}{}.frames",
+            self.key, key.resolution.width, key.resolution.height, backdrop
+        ))
+    }
+}
+
+/// Removes all cached frames.
+pub fn clear() -> Result<(), String> {
+    let dir = match cache_dir() {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    fs::remove_dir_all(&dir).map_err(|e| format!("Unable to clear cache {}: {}", dir.display(), e))
+}
+
+/// Removes the least recently modified cache-files, until all remaining ones
+/// fit into `limit` bytes. The file at `keep` is never removed.
+fn prune(dir: &Path, limit: u64, keep: &Path) -> std::io::Result<()> {
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path
+            .extension()
+            .map_or(true, |extension| extension != "frames")
+        {
+            continue;
+        }
+
+        let metadata = fs::metadata(&path)?;
+        files.push((metadata.modified()?, metadata.len(), path));
+    }
+
+    let mut size: u64 = files.iter().map(|(_, len, _)| len).sum();
+
+    // Oldest first
+    files.sort();
+
+    for (_, len, path) in files {
+        if size <= limit {
+            break;
+        }
+
+        if path != keep {
+            fs::remove_file(&path)?;
+            size -= len;
+        }
+    }
+
+    Ok(())
+}
+
+/// Directory of the cache, following the XDG base directory specification.
+fn cache_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+
+    Some(base.join("xgifwallpaper"))
+}
+
+/// Hash of the GIF-content and all options, which affect the rendered frames.
+/// The content includes the background-image, if any.
+fn cache_key(content_hash: u64, options: &Options, format: &PixelFormat) -> u64 {
+    // Alignment and offset affect frames only by the background-image behind
+    let background_placement = options
+        .background_image
+        .as_ref()
+        .map(|_| (options.alignment, options.offset));
+
+    let params = format!(
+        "{}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{}|{:?}|{:?}|{}|{:?}",
+        FORMAT_VERSION,
+        background_placement,
+        options.crop,
+        options.scaling,
+        options.target_size,
+        options.scaling_filter,
+        options.upscaling_filter,
+        options.downscaling_filter,
+        options.linear_light,
+        format.background_rgba,
+        format.rgba_indices,
+        options.default_delay,
+        options.frame_delays
+    );
+
+    fnv1a(content_hash, params.as_bytes())
+}
+
+/// Feeds the content of the file into the hash, without reading it into
+/// memory at once.
+fn hash_file(hash: u64, path: &Path) -> Result<u64, String> {
+    File::open(path)
+        .and_then(|file| {
+            let length = file.metadata()?.len();
+            hash_content(hash, length, BufReader::new(file))
+        })
+        .map_err(|e| format!("Unable to read {}: {}", path.display(), e))
+}
+
+/// Feeds the length and the content of a file into the hash, chunk by chunk.
+/// The length marks the end of each file, so the content of concatenated files
+/// doesn't collide.
+fn hash_content<R: BufRead>(hash: u64, length: u64, mut reader: R) -> std::io::Result<u64> {
+    let mut hash = fnv1a(hash, &length.to_le_bytes());
+
+    loop {
+        let chunk = reader.fill_buf()?;
+
+        if chunk.is_empty() {
+            return Ok(hash);
+        }
+
+        hash = fnv1a(hash, chunk);
+
+        let consumed = chunk.len();
+        reader.consume(consumed);
+    }
+}
+
+/// 64-bit FNV-1a hash. Stable across builds, unlike the hasher of `std`.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+fn write_header<W: Write>(
+    writer: &mut W,
+    resolution: &Resolution,
+    frame_count: usize,
+) -> std::io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    writer.write_all(&resolution.width.to_le_bytes())?;
+    writer.write_all(&resolution.height.to_le_bytes())?;
+    writer.write_all(&(frame_count as u32).to_le_bytes())
+}
+
+/// Reads and validates the header. Returns the number of frames.
+fn read_header<R: Read>(reader: &mut R, resolution: &Resolution) -> Result<usize, String> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic).map_err(|e| e.to_string())?;
+
+    let version = read_u32(reader).map_err(|e| e.to_string())?;
+    let width = read_u32(reader).map_err(|e| e.to_string())?;
+    let height = read_u32(reader).map_err(|e| e.to_string())?;
+    let frame_count = read_u32(reader).map_err(|e| e.to_string())?;
+
+    if &magic != MAGIC || version != FORMAT_VERSION {
+        return Err("Unknown format of cache-file".to_string());
+    }
+
+    if width != resolution.width || height != resolution.height || frame_count == 0 {
+        return Err("Cache-file does not match resolution".to_string());
+    }
+
+    Ok(frame_count as usize)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn when_hashing_then_match_fnv1a_reference() {
+        assert_eq!(fnv1a(FNV_OFFSET, b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(FNV_OFFSET, b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn when_options_affecting_pixels_differ_then_keys_differ() {
+        let format = _format();
+        let key = cache_key(_hash(b"GIF"), &_options(vec![]), &format);
+
+        assert_eq!(key, cache_key(_hash(b"GIF"), &_options(vec![]), &format));
+        assert_ne!(key, cache_key(_hash(b"GIF89a"), &_options(vec![]), &format));
+        assert_ne!(
+            key,
+            cache_key(_hash(b"GIF"), &_options(vec!["-s", "MAX"]), &format)
+        );
+        assert_ne!(
+            key,
+            cache_key(_hash(b"GIF"), &_options(vec!["--zoom", "2"]), &format)
+        );
+        assert_ne!(
+            key,
+            cache_key(_hash(b"GIF"), &_options(vec!["--crop", "0,0,1,1"]), &format)
+        );
+        assert_ne!(
+            key,
+            cache_key(
+                _hash(b"GIF"),
+                &_options(vec!["--scale-filter", "PIXEL"]),
+                &format
+            )
+        );
+        assert_ne!(
+            key,
+            cache_key(
+                _hash(b"GIF"),
+                &_options(vec!["--upscale-filter", "PIXEL"]),
+                &format
+            )
+        );
+        assert_ne!(
+            key,
+            cache_key(
+                _hash(b"GIF"),
+                &_options(vec!["--downscale-filter", "PIXEL"]),
+                &format
+            )
+        );
+        assert_ne!(
+            key,
+            cache_key(
+                _hash(b"GIF"),
+                &_options(vec!["--background-image", "a.png"]),
+                &format
+            )
+        );
+        assert_ne!(
+            key,
+            cache_key(_hash(b"GIF"), &_options(vec!["-d", "20"]), &format)
+        );
+        assert_ne!(
+            key,
+            cache_key(
+                _hash(b"GIF"),
+                &_options(vec!["--frame-delays", "20"]),
+                &format
+            )
+        );
+
+        let mut bgra = _format();
+        bgra.rgba_indices = [2, 1, 0, 3];
+        assert_ne!(key, cache_key(_hash(b"GIF"), &_options(vec![]), &bgra));
+
+        let mut white = _format();
+        white.background_rgba = [255, 255, 255, 255];
+        assert_ne!(key, cache_key(_hash(b"GIF"), &_options(vec![]), &white));
+    }
+
+    #[test]
+    fn when_files_are_hashed_then_tell_apart_their_boundaries() {
+        let ab_c = _hash_files(&[b"ab", b"c"]);
+
+        assert_eq!(ab_c, _hash_files(&[b"ab", b"c"]));
+        assert_ne!(ab_c, _hash_files(&[b"a", b"bc"]));
+        assert_ne!(ab_c, _hash_files(&[b"abc"]));
+    }
+
+    #[test]
+    fn when_file_is_hashed_then_read_it_chunk_by_chunk() {
+        let content = vec![42; 100_000];
+        let reader = BufReader::with_capacity(64, content.as_slice());
+
+        assert_eq!(
+            hash_content(FNV_OFFSET, 100_000, reader).unwrap(),
+            _hash_files(&[&content])
+        );
+    }
+
+    #[test]
+    fn when_header_is_written_then_read_frame_count() {
+        let resolution = Resolution::new(1920, 1080);
+        let mut bytes: Vec<u8> = Vec::new();
+        write_header(&mut bytes, &resolution, 42).unwrap();
+
+        assert_eq!(read_header(&mut bytes.as_slice(), &resolution), Ok(42));
+    }
+
+    #[test]
+    fn when_header_has_other_resolution_then_fail() {
+        let mut bytes: Vec<u8> = Vec::new();
+        write_header(&mut bytes, &Resolution::new(1920, 1080), 42).unwrap();
+
+        assert!(read_header(&mut bytes.as_slice(), &Resolution::new(1080, 1920)).is_err());
+    }
+
+    #[test]
+    fn when_header_is_not_a_cache_file_then_fail() {
+        let bytes = b"GIF89a is not a cache-file at all".to_vec();
+
+        assert!(read_header(&mut bytes.as_slice(), &Resolution::new(1, 1)).is_err());
+    }
+
+    #[test]
+    fn when_cache_exceeds_limit_then_remove_oldest_files() {
+        let dir = std::env::temp_dir().join(format!("xgifwallpaper-prune-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let now = SystemTime::now();
+        let names = ["old.frames", "used.frames", "new.frames", "other.txt"];
+
+        for (age, name) in names.iter().enumerate() {
+            let file = File::create(dir.join(name)).unwrap();
+            file.set_len(100).unwrap();
+            file.set_modified(now - Duration::from_secs(100 - age as u64 * 10))
+                .unwrap();
+        }
+
+        prune(&dir, 200, &dir.join("old.frames")).unwrap();
+
+        assert!(dir.join("old.frames").exists(), "Kept, as just written");
+        assert!(!dir.join("used.frames").exists());
+        assert!(dir.join("new.frames").exists());
+        assert!(dir.join("other.txt").exists(), "Not a cache-file");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn when_frames_are_stored_then_load_them() {
+        let dir = std::env::temp_dir().join(format!("xgifwallpaper-store-{}", std::process::id()));
+        let cache = Cache { dir, key: 42 };
+
+        let key = FrameSetKey {
+            path: "a.gif".to_string(),
+            resolution: Resolution::new(1, 2),
+            backdrop: None,
+        };
+        let frames = vec![RenderedFrame {
+            delay: Duration::from_millis(70),
+            raster: vec![1, 2, 3, 4, 5, 6, 7, 8],
+        }];
+
+        cache.store(&key, &frames).unwrap();
+        let loaded = cache.load(&key).unwrap();
+
+        assert_eq!(cache.frame_count(&key), Some(1));
+        assert_eq!(loaded[0].delay, frames[0].delay);
+        assert_eq!(loaded[0].raster, frames[0].raster);
+
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    fn _hash(content: &[u8]) -> u64 {
+        _hash_files(&[content])
+    }
+
+    fn _hash_files(contents: &[&[u8]]) -> u64 {
+        contents.iter().fold(FNV_OFFSET, |hash, content| {
+            hash_content(hash, content.len() as u64, *content).unwrap()
+        })
+    }
+
+    fn _options(custom_params: Vec<&str>) -> Options {
+        Options::_from_params([vec!["xgifwallpaper"], custom_params, vec!["a.gif"]].concat())
+    }
+
+    fn _format() -> PixelFormat {
+        PixelFormat {
+            rgba_indices: [0, 1, 2, 3],
+            background_rgba: [0, 0, 0, 255],
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Composes the partial frames of an animation onto a full-size canvas,
+//! respecting their offsets and disposal-methods.
+
+/// What happens with the area of a frame, before the next frame is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Disposal {
+    /// No disposal specified. Handled like `KEEP`.
+    NONE,
+    /// Leave the frame in place.
+    KEEP,
+    /// Clear the area of the frame to transparent background.
+    BACKGROUND,
+    /// Restore the area of the frame to the state before it was drawn.
+    PREVIOUS,
+}
+
+/// Partial image of an animation, placed somewhere on the canvas.
+#[derive(Debug)]
+pub struct Patch {
+    /// Offset on x-axis of the canvas.
+    pub left: u32,
+    /// Offset on y-axis of the canvas.
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+    /// RGBA-pixels, row by row. Alpha of 0 marks a transparent pixel.
+    pub rgba: Vec<u8>,
+    /// Disposal of this patch, before the next one is drawn.
+    pub disposal: Disposal,
+}
+
+/// Rectangle of a canvas, already clipped to its bounds.
+#[derive(Clone, Copy, Debug)]
+struct Area {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Canvas and state to compose patches in order.
+pub struct Compositor {
+    width: usize,
+    height: usize,
+    canvas: Vec<u8>,
+    /// Disposal of the last drawn patch, applied before the next is drawn.
+    pending_disposal: Option<(Disposal, Area)>,
+    /// Canvas before drawing the last patch, if it must be restored.
+    saved_canvas: Option<Vec<u8>>,
+}
+
+impl Compositor {
+    /// Creates a transparent canvas of given size.
+    pub fn new(width: u32, height: u32) -> Compositor {
+        Compositor {
+            width: width as usize,
+            height: height as usize,
+            canvas: vec![0; width as usize * height as usize * 4],
+            pending_disposal: None,
+            saved_canvas: None,
+        }
+    }
+
+    /// Disposes the previous patch, draws the given one and returns a copy of
+    /// the resulting RGBA-canvas.
+    pub fn compose(&mut self, patch: &Patch) -> Vec<u8> {
+        self.dispose_previous();
+
+        let area = self.clip(patch);
+
+        if patch.disposal == Disposal::PREVIOUS {
+            self.saved_canvas = Some(self.canvas.clone());
+        }
+
+        for row in 0..area.height {
+            let src_row = (row * patch.width as usize) * 4;
+            let dst_row = ((area.y + row) * self.width + area.x) * 4;
+
+            for col in 0..area.width {
+                let src = src_row + col * 4;
+                let dst = dst_row + col * 4;
+
+                if src + 4 > patch.rgba.len() {
+                    break;
+                }
+
+                blend_over(&mut self.canvas[dst..dst + 4], &patch.rgba[src..src + 4]);
+            }
+        }
+
+        self.pending_disposal = Some((patch.disposal, area));
+
+        self.canvas.clone()
+    }
+
+    /// Applies the disposal of the last drawn patch.
+    fn dispose_previous(&mut self) {
+        let (disposal, area) = match self.pending_disposal.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+
+        match disposal {
+            Disposal::NONE | Disposal::KEEP => {}
+            Disposal::BACKGROUND => self.fill_area(&area, None),
+            Disposal::PREVIOUS => {
+                if let Some(saved) = self.saved_canvas.take() {
+                    self.fill_area(&area, Some(&saved));
+                }
+            }
+        }
+    }
+
+    /// Fills area with pixels of `source` or transparency, if `None`.
+    fn fill_area(&mut self, area: &Area, source: Option<&Vec<u8>>) {
+        for row in area.y..(area.y + area.height) {
+            let start = (row * self.width + area.x) * 4;
+            let end = start + area.width * 4;
+
+            match source {
+                Some(source) => self.canvas[start..end].copy_from_slice(&source[start..end]),
+                None => self.canvas[start..end].iter_mut().for_each(|b| *b = 0),
+            }
+        }
+    }
+
+    /// Intersection of the patch with the canvas.
+    fn clip(&self, patch: &Patch) -> Area {
+        let x = (patch.left as usize).min(self.width);
+        let y = (patch.top as usize).min(self.height);
+
+        Area {
+            x,
+            y,
+            width: (patch.width as usize).min(self.width - x),
+            height: (patch.height as usize).min(self.height - y),
+        }
+    }
+}
+
+/// Draws RGBA-pixel `src` over `dst`, respecting the alpha of both.
+fn blend_over(dst: &mut [u8], src: &[u8]) {
+    let src_alpha = src[3] as u32;
+
+    if src_alpha == 255 {
+        dst.copy_from_slice(src);
+        return;
+    }
+
+    if src_alpha == 0 {
+        return;
+    }
+
+    let dst_alpha = dst[3] as u32 * (255 - src_alpha) / 255;
+    let out_alpha = src_alpha + dst_alpha;
+
+    for c in 0..3 {
+        dst[c] = ((src[c] as u32 * src_alpha + dst[c] as u32 * dst_alpha) / out_alpha) as u8;
+    }
+
+    dst[3] = out_alpha as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compositor;
+    use super::Disposal;
+    use super::Patch;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+    #[test]
+    fn when_first_patch_is_partial_then_rest_of_canvas_is_transparent() {
+        let mut compositor = Compositor::new(2, 2);
+
+        let canvas = compositor.compose(&_patch(1, 1, 1, 1, RED, Disposal::NONE));
+
+        assert_eq!(_pixel(&canvas, 2, 0, 0), TRANSPARENT);
+        assert_eq!(_pixel(&canvas, 2, 1, 1), RED);
+    }
+
+    #[test]
+    fn when_disposal_is_keep_then_next_patch_is_drawn_over_it() {
+        let mut compositor = Compositor::new(2, 2);
+
+        compositor.compose(&_patch(0, 0, 2, 2, RED, Disposal::KEEP));
+        let canvas = compositor.compose(&_patch(1, 1, 1, 1, BLUE, Disposal::KEEP));
+
+        assert_eq!(_pixel(&canvas, 2, 0, 0), RED);
+        assert_eq!(_pixel(&canvas, 2, 1, 1), BLUE);
+    }
+
+    #[test]
+    fn when_disposal_is_background_then_area_is_cleared_before_next_patch() {
+        let mut compositor = Compositor::new(3, 3);
+
+        compositor.compose(&_patch(0, 0, 3, 3, RED, Disposal::KEEP));
+        compositor.compose(&_patch(0, 0, 2, 2, GREEN, Disposal::BACKGROUND));
+        let canvas = compositor.compose(&_patch(2, 2, 1, 1, BLUE, Disposal::KEEP));
+
+        assert_eq!(_pixel(&canvas, 3, 0, 0), TRANSPARENT);
+        assert_eq!(_pixel(&canvas, 3, 1, 1), TRANSPARENT);
+        assert_eq!(_pixel(&canvas, 3, 2, 0), RED);
+        assert_eq!(_pixel(&canvas, 3, 2, 2), BLUE);
+    }
+
+    #[test]
+    fn when_disposal_is_previous_then_area_is_restored_before_next_patch() {
+        let mut compositor = Compositor::new(3, 3);
+
+        compositor.compose(&_patch(0, 0, 3, 3, RED, Disposal::KEEP));
+        let canvas = compositor.compose(&_patch(0, 0, 2, 2, GREEN, Disposal::PREVIOUS));
+        assert_eq!(_pixel(&canvas, 3, 1, 1), GREEN);
+
+        let canvas = compositor.compose(&_patch(2, 2, 1, 1, BLUE, Disposal::KEEP));
+
+        assert_eq!(_pixel(&canvas, 3, 0, 0), RED);
+        assert_eq!(_pixel(&canvas, 3, 1, 1), RED);
+        assert_eq!(_pixel(&canvas, 3, 2, 2), BLUE);
+    }
+
+    #[test]
+    fn when_disposal_of_current_patch_is_background_then_it_is_still_drawn() {
+        let mut compositor = Compositor::new(1, 1);
+
+        let canvas = compositor.compose(&_patch(0, 0, 1, 1, RED, Disposal::BACKGROUND));
+
+        assert_eq!(_pixel(&canvas, 1, 0, 0), RED);
+    }
+
+    #[test]
+    fn when_patch_has_transparent_pixels_then_keep_canvas_below() {
+        let mut compositor = Compositor::new(2, 1);
+
+        compositor.compose(&_patch(0, 0, 2, 1, RED, Disposal::KEEP));
+
+        let patch = Patch {
+            left: 0,
+            top: 0,
+            width: 2,
+            height: 1,
+            rgba: [TRANSPARENT, BLUE].concat(),
+            disposal: Disposal::KEEP,
+        };
+        let canvas = compositor.compose(&patch);
+
+        assert_eq!(_pixel(&canvas, 2, 0, 0), RED);
+        assert_eq!(_pixel(&canvas, 2, 1, 0), BLUE);
+    }
+
+    #[test]
+    fn when_patch_exceeds_canvas_then_clip_it() {
+        let mut compositor = Compositor::new(2, 2);
+
+        let canvas = compositor.compose(&_patch(1, 1, 3, 3, RED, Disposal::BACKGROUND));
+        assert_eq!(canvas.len(), 2 * 2 * 4);
+        assert_eq!(_pixel(&canvas, 2, 1, 1), RED);
+
+        let canvas = compositor.compose(&_patch(0, 0, 1, 1, BLUE, Disposal::KEEP));
+        assert_eq!(_pixel(&canvas, 2, 0, 0), BLUE);
+        assert_eq!(_pixel(&canvas, 2, 1, 1), TRANSPARENT);
+    }
+
+    fn _patch(
+        left: u32,
+        top: u32,
+        width: u32,
+        height: u32,
+        color: [u8; 4],
+        disposal: Disposal,
+    ) -> Patch {
+        Patch {
+            left,
+            top,
+            width,
+            height,
+            rgba: color.repeat((width * height) as usize),
+            disposal,
+        }
+    }
+
+    fn _pixel(canvas: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * width + x) * 4;
+        [canvas[i], canvas[i + 1], canvas[i + 2], canvas[i + 3]]
+    }
+}
@@ -0,0 +1,382 @@
+//! Runtime control of a running instance over a Unix-domain socket.
+//!
+//! A client sends a single line with a command and reads the reply until the
+//! connection is closed. Replies of failed commands start with `error:`.
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use crate::options::Options;
+use crate::sequence;
+
+const EXIT_NO_INSTANCE: i32 = 105;
+const EXIT_COMMAND_FAILED: i32 = 106;
+
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Slowest and fastest speed to play with, keeping delays of frames in range.
+const MIN_SPEED: f32 = 0.01;
+const MAX_SPEED: f32 = 100.0;
+
+const USAGE: &str = "Usage: xgifwallpaper ctl <COMMAND>
+
+Commands:
+    pause                 Pause the animation on all screens.
+    resume                Resume the animation.
+    next-frame            Show the next frame on all screens, also if paused.
+    set-speed <factor>    Play with given speed from 0.01 to 100, 1 is the speed
+                          of the file.
+    status                Show state, speed and current frames.
+    swap <file>           Show another file on all screens, once rendered.";
+
+/// Commands to control the animation.
+#[allow(non_camel_case_types)]
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    PAUSE,
+    RESUME,
+    /// Show the next frame of each screen now, also if paused.
+    NEXT_FRAME,
+    /// Factor to divide delays of frames by.
+    SET_SPEED(f32),
+    STATUS,
+    /// Path of the file to show on all screens instead.
+    SWAP(String),
+}
+
+impl Command {
+    /// Parses a command-line like `set-speed 1.5`. Fails with a message for
+    /// the client.
+    pub fn parse(line: &str) -> Result<Command, String> {
+        // Paths may contain whitespace, so take all of the rest
+        if let Some(path) = line.trim().strip_prefix("swap ") {
+            return Ok(Command::SWAP(path.trim().to_string()));
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["pause"] => Ok(Command::PAUSE),
+            ["resume"] => Ok(Command::RESUME),
+            ["next-frame"] => Ok(Command::NEXT_FRAME),
+            ["set-speed", speed] => speed
+                .parse::<f32>()
+                .ok()
+                .filter(|speed| (MIN_SPEED..=MAX_SPEED).contains(speed))
+                .map(Command::SET_SPEED)
+                .ok_or_else(|| {
+                    format!(
+                        "Speed must be a number from {} to {}, not {}",
+                        MIN_SPEED, MAX_SPEED, speed
+                    )
+                }),
+            ["status"] => Ok(Command::STATUS),
+            _ => Err(format!("Unknown command \"{}\"", line.trim())),
+        }
+    }
+}
+
+/// Command received from a client. The reply is sent back to it.
+pub struct Request {
+    pub command: Command,
+    pub reply: Sender<String>,
+}
+
+/// Accepts commands on the control-socket in a background-thread. The socket
+/// is removed on drop.
+pub struct ControlServer {
+    /// Commands in order received, to be handled by the animation-loop.
+    pub commands: Receiver<Request>,
+    path: Option<PathBuf>,
+}
+
+impl ControlServer {
+    /// Listens on the control-socket of the session. The thread handling the
+    /// commands is woken up on each one. Without a socket, no commands are
+    /// ever received.
+    pub fn start(options: Arc<Options>, handler: Thread) -> ControlServer {
+        let path = socket_path();
+
+        match ControlServer::start_at(&path, handler) {
+            Ok(server) => {
+                logln!(options, "Listen for commands on {}", path.display());
+                server
+            }
+            Err(e) => {
+                eprintln!("Control-socket is not available: {}", e);
+
+                let (_, commands) = channel();
+                ControlServer {
+                    commands,
+                    path: None,
+                }
+            }
+        }
+    }
+
+    fn start_at(path: &Path, handler: Thread) -> Result<ControlServer, String> {
+        // A socket is left over by an instance, which was not shut down
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(format!("{} is used by another instance", path.display()));
+            }
+
+            let _ = fs::remove_file(path);
+        }
+
+        let listener = UnixListener::bind(path)
+            .map_err(|e| format!("Unable to listen on {}: {}", path.display(), e))?;
+
+        let (sender, commands) = channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                handle_client(stream, &sender, &handler);
+            }
+        });
+
+        Ok(ControlServer {
+            commands,
+            path: Some(path.to_path_buf()),
+        })
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Passes the command of a client to the handler and writes its reply back.
+fn handle_client(stream: UnixStream, sender: &Sender<Request>, handler: &Thread) {
+    let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+
+    let mut line = String::new();
+    let mut reader = BufReader::new(&stream);
+
+    let reply = match reader.read_line(&mut line) {
+        Ok(_) => match Command::parse(&line) {
+            Ok(command) => {
+                let (reply, replies) = channel();
+
+                let _ = sender.send(Request { command, reply });
+                handler.unpark();
+
+                replies
+                    .recv_timeout(CLIENT_TIMEOUT)
+                    .unwrap_or_else(|_| "error: No reply, while rendering".to_string())
+            }
+            Err(e) => format!("error: {}", e),
+        },
+        Err(e) => format!("error: {}", e),
+    };
+
+    let mut stream = &stream;
+    let _ = writeln!(stream, "{}", reply);
+}
+
+/// Sends a command to the running instance and prints its reply. Returns the
+/// exit-code of the client.
+pub fn ctl(args: &[String]) -> i32 {
+    if args.is_empty() || args[0] == "help" || args[0] == "--help" {
+        println!("{}", USAGE);
+        return 0;
+    }
+
+    let mut line = args.join(" ");
+
+    // The instance runs in another directory, so send an absolute path
+    if args[0] == "swap" && args.len() > 1 {
+        match absolute_path(&args[1..].join(" ")) {
+            Ok(absolute) => line = format!("swap {}", absolute),
+            Err(e) => {
+                eprintln!("{}", e);
+                return EXIT_COMMAND_FAILED;
+            }
+        }
+    }
+
+    let path = socket_path();
+
+    match request(&path, &line) {
+        Ok(reply) if reply.starts_with("error:") => {
+            eprint!("{}", reply);
+            EXIT_COMMAND_FAILED
+        }
+        Ok(reply) => {
+            print!("{}", reply);
+            0
+        }
+        Err(e) => {
+            eprintln!("No running xgifwallpaper at {}: {}", path.display(), e);
+            EXIT_NO_INSTANCE
+        }
+    }
+}
+
+/// Canonical path of an existing file or directory. Of a glob, only its
+/// directory must exist.
+fn absolute_path(path: &str) -> Result<String, String> {
+    let as_path = Path::new(path);
+
+    let absolute = match (sequence::is_glob(path), as_path.file_name()) {
+        (true, Some(pattern)) => {
+            let directory = match as_path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+
+            fs::canonicalize(directory).map(|directory| directory.join(pattern))
+        }
+        _ => fs::canonicalize(as_path),
+    };
+
+    absolute
+        .map(|absolute| absolute.to_string_lossy().into_owned())
+        .map_err(|e| format!("Unable to swap to {}: {}", path, e))
+}
+
+/// Sends the command-line and reads the whole reply.
+fn request(path: &Path, line: &str) -> std::io::Result<String> {
+    let mut stream = UnixStream::connect(path)?;
+
+    writeln!(stream, "{}", line)?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+
+    Ok(reply)
+}
+
+/// Path of the control-socket for the X-display of the session. Within
+/// `XDG_RUNTIME_DIR`, otherwise the directory for temporary files.
+pub fn socket_path() -> PathBuf {
+    let display: String = env::var("DISPLAY")
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => {
+            PathBuf::from(dir).join(format!("xgifwallpaper{}.sock", display))
+        }
+        _ => env::temp_dir().join(format!(
+            "xgifwallpaper-{}{}.sock",
+            unsafe { libc::getuid() },
+            display
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{absolute_path, request, Command, ControlServer};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn when_command_is_known_then_parse_it() {
+        assert_eq!(Command::parse("pause\n"), Ok(Command::PAUSE));
+        assert_eq!(Command::parse("resume"), Ok(Command::RESUME));
+        assert_eq!(Command::parse("next-frame"), Ok(Command::NEXT_FRAME));
+        assert_eq!(Command::parse("set-speed 1.5"), Ok(Command::SET_SPEED(1.5)));
+        assert_eq!(Command::parse(" status "), Ok(Command::STATUS));
+        assert_eq!(
+            Command::parse("swap my wallpaper.gif\n"),
+            Ok(Command::SWAP("my wallpaper.gif".to_string()))
+        );
+    }
+
+    #[test]
+    fn when_command_is_invalid_then_fail() {
+        assert!(Command::parse("").is_err());
+        assert!(Command::parse("stop").is_err());
+        assert!(Command::parse("pause now").is_err());
+        assert!(Command::parse("set-speed").is_err());
+        assert!(Command::parse("set-speed 0").is_err());
+        assert!(Command::parse("set-speed fast").is_err());
+        assert!(Command::parse("swap").is_err());
+    }
+
+    #[test]
+    fn when_swapped_path_is_relative_then_make_it_absolute() {
+        let samples = std::fs::canonicalize("tests/samples").unwrap();
+
+        assert_eq!(
+            absolute_path("tests/samples/sample-still.bmp"),
+            Ok(samples
+                .join("sample-still.bmp")
+                .to_string_lossy()
+                .into_owned())
+        );
+        assert_eq!(
+            absolute_path("tests/samples/*.png"),
+            Ok(samples.join("*.png").to_string_lossy().into_owned())
+        );
+    }
+
+    #[test]
+    fn when_swapped_path_does_not_exist_then_fail() {
+        assert!(absolute_path("tests/samples/no-such-file.gif").is_err());
+        assert!(absolute_path("no-such-dir/*.png").is_err());
+    }
+
+    #[test]
+    fn when_speed_is_within_limits_then_accept_it() {
+        assert_eq!(
+            Command::parse("set-speed 0.01"),
+            Ok(Command::SET_SPEED(0.01))
+        );
+        assert_eq!(
+            Command::parse("set-speed 100"),
+            Ok(Command::SET_SPEED(100.0))
+        );
+    }
+
+    #[test]
+    fn when_speed_is_beyond_limits_then_fail() {
+        assert!(Command::parse("set-speed 0.009").is_err());
+        assert!(Command::parse("set-speed 1e-20").is_err());
+        assert!(Command::parse("set-speed 100.1").is_err());
+        assert!(Command::parse("set-speed inf").is_err());
+    }
+
+    #[test]
+    fn when_client_sends_command_then_reply_of_handler_is_returned() {
+        let path =
+            std::env::temp_dir().join(format!("xgifwallpaper-test-{}.sock", std::process::id()));
+
+        let server = ControlServer::start_at(&path, thread::current()).unwrap();
+
+        let client_path = path.clone();
+        let client = thread::spawn(move || request(&client_path, "set-speed 2").unwrap());
+
+        let received = server
+            .commands
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(received.command, Command::SET_SPEED(2.0));
+        received.reply.send("ok".to_string()).unwrap();
+
+        assert_eq!(client.join().unwrap(), "ok\n");
+
+        let reply = request(&path, "jump").unwrap();
+        assert!(reply.starts_with("error:"), "Reply was {}", reply);
+
+        drop(server);
+        assert!(!path.exists(), "Socket is removed on drop");
+    }
+}
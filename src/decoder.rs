@@ -0,0 +1,487 @@
+//! Decodes image-files into a sequence of composed, full-size frames. The
+//! format is chosen by the signature of the file.
+
+use std::fs::File;
+use std::io::Read;
+
+use gift::block::DisposalMethod;
+
+use crate::compositor::{Compositor, Disposal, Patch};
+use crate::image_frames::ImageFrames;
+use crate::position::Resolution;
+use crate::sequence::{self, SequenceFrames};
+
+/// Bytes read to detect the format, enough for the header of a BMP.
+const BMP_HEADER_SIZE: usize = 18;
+
+/// Sizes of all known DIB-headers, following the file-header of a BMP. OS/2
+/// headers of version 2 may be cut short to 16 bytes or anything up to 64.
+const DIB_HEADER_SIZES: [u32; 8] = [12, 16, 40, 52, 56, 64, 108, 124];
+
+/// Formats of image-files, known by their signature.
+#[derive(Debug, PartialEq)]
+enum FileFormat {
+    BMP,
+    GIF,
+    JPEG,
+    PNG,
+    WEBP,
+}
+
+/// Full-size frame of an animation, composed with all frames before.
+pub struct ComposedFrame {
+    /// RGBA-pixels of the whole canvas. Alpha of 0 marks a transparent pixel.
+    pub raster: Vec<u8>,
+    /// Delay in centiseconds, if specified.
+    pub delay_cs: Option<u16>,
+}
+
+/// Source of composed frames, independent of the format of the file.
+pub trait FrameSource: Iterator<Item = Result<ComposedFrame, String>> {
+    /// Size of the canvas, all frames are composed on.
+    fn resolution(&self) -> &Resolution;
+}
+
+/// Opens the image-file and reads its header, with the decoder matching its
+/// signature. A directory or glob is opened as image-sequence. Fails, if the
+/// file is not readable or of an unknown format.
+pub fn open_frames(path: &str) -> Result<Box<dyn FrameSource>, String> {
+    if sequence::is_sequence(path) {
+        return Ok(Box::new(SequenceFrames::open(path)?));
+    }
+
+    let mut signature = [0; BMP_HEADER_SIZE];
+    let (signature_len, file_size) = File::open(path)
+        .and_then(|mut file| Ok((file.read(&mut signature)?, file.metadata()?.len())))
+        .map_err(|e| format!("Unable to read file {}: {}", path, e))?;
+
+    match detect_format(&signature[..signature_len], file_size) {
+        Some(FileFormat::GIF) => Ok(Box::new(GifFrames::open(path)?)),
+        Some(FileFormat::PNG) => Ok(Box::new(ImageFrames::open_png(path)?)),
+        Some(FileFormat::WEBP) => Ok(Box::new(ImageFrames::open_webp(path)?)),
+        Some(FileFormat::JPEG) => Ok(Box::new(ImageFrames::open_jpeg(path)?)),
+        Some(FileFormat::BMP) => Ok(Box::new(ImageFrames::open_bmp(path)?)),
+        None => Err(format!(
+            "File {} is neither a GIF, PNG, WebP, JPEG nor BMP",
+            path
+        )),
+    }
+}
+
+/// Detect format of a file by its first bytes. A BMP has no distinctive
+/// signature, so its header must be plausible as well.
+fn detect_format(signature: &[u8], file_size: u64) -> Option<FileFormat> {
+    if signature.starts_with(b"GIF87a") || signature.starts_with(b"GIF89a") {
+        Some(FileFormat::GIF)
+    } else if signature.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(FileFormat::PNG)
+    } else if signature.starts_with(b"RIFF") && signature.get(8..12) == Some(b"WEBP") {
+        Some(FileFormat::WEBP)
+    } else if signature.starts_with(b"\xff\xd8\xff") {
+        Some(FileFormat::JPEG)
+    } else if is_bmp_header(signature, file_size) {
+        Some(FileFormat::BMP)
+    } else {
+        None
+    }
+}
+
+/// `true`, if the first bytes are a BMP file-header, followed by a DIB-header
+/// of known size. The size of the file it states is a hint only: Some
+/// encoders write 0 or pad the file.
+fn is_bmp_header(signature: &[u8], file_size: u64) -> bool {
+    if signature.len() < BMP_HEADER_SIZE || !signature.starts_with(b"BM") {
+        return false;
+    }
+
+    let field = |offset: usize| {
+        u32::from_le_bytes([
+            signature[offset],
+            signature[offset + 1],
+            signature[offset + 2],
+            signature[offset + 3],
+        ])
+    };
+
+    field(2) as u64 <= file_size && DIB_HEADER_SIZES.contains(&field(14))
+}
+
+/// Decoded animation, held in memory with all frames in full size.
+pub struct Animation {
+    /// Resolution of all frames.
+    pub resolution: Resolution,
+    pub frames: Vec<ComposedFrame>,
+}
+
+impl Animation {
+    /// Decodes and composes all remaining frames of an opened file. Stops at
+    /// the first broken frame, but fails if not a single frame was decoded.
+    pub fn from_source(source: Box<dyn FrameSource>) -> Result<Animation, String> {
+        let resolution = source.resolution().clone();
+
+        let mut frames: Vec<ComposedFrame> = Vec::new();
+
+        for frame in source {
+            match frame {
+                Ok(frame) => frames.push(frame),
+                Err(e) if frames.is_empty() => return Err(e),
+                Err(e) => {
+                    eprintln!("Ignore frames after {}: {}", frames.len(), e);
+                    break;
+                }
+            }
+        }
+
+        if frames.is_empty() {
+            return Err("Contains no frames".to_string());
+        }
+
+        Ok(Animation { resolution, frames })
+    }
+
+    /// Replaces the delays of the first frames by the given ones, in
+    /// centiseconds. Frames beyond keep their delay.
+    pub fn override_delays(&mut self, delays_cs: &[u16]) {
+        for (frame, delay_cs) in self.frames.iter_mut().zip(delays_cs) {
+            frame.delay_cs = Some(*delay_cs);
+        }
+    }
+}
+
+/// Iterator over the composed frames of a GIF-file.
+pub struct GifFrames {
+    frames: gift::decode::Frames<File>,
+    global_color_table: Option<Vec<u8>>,
+    compositor: Compositor,
+    resolution: Resolution,
+}
+
+impl GifFrames {
+    /// Opens the GIF-file and reads its preamble. Fails, if the file is not
+    /// readable or not a GIF.
+    pub fn open(path_to_gif: &str) -> Result<GifFrames, String> {
+        let file = File::open(path_to_gif)
+            .map_err(|e| format!("Unable to read file {}: {}", path_to_gif, e))?;
+
+        let mut frames = gift::Decoder::new(file).into_frames();
+
+        let preamble = match frames.preamble() {
+            Ok(Some(preamble)) => preamble,
+            Ok(None) => return Err(format!("File {} is not a valid GIF", path_to_gif)),
+            Err(e) => return Err(format!("File {} is not a valid GIF: {:?}", path_to_gif, e)),
+        };
+
+        let resolution = Resolution::new(
+            preamble.logical_screen_desc.screen_width() as u32,
+            preamble.logical_screen_desc.screen_height() as u32,
+        );
+
+        Ok(GifFrames {
+            frames,
+            global_color_table: preamble
+                .global_color_table
+                .map(|table| table.colors().to_vec()),
+            compositor: Compositor::new(resolution.width, resolution.height),
+            resolution,
+        })
+    }
+}
+
+impl Iterator for GifFrames {
+    type Item = Result<ComposedFrame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = match self.frames.next()? {
+            Ok(frame) => frame,
+            Err(e) => return Some(Err(format!("Unable to decode frame: {:?}", e))),
+        };
+
+        let patch = to_patch(&frame, self.global_color_table.as_deref());
+
+        Some(Ok(ComposedFrame {
+            raster: self.compositor.compose(&patch),
+            delay_cs: frame.graphic_control_ext.map(|gce| gce.delay_time_cs()),
+        }))
+    }
+}
+
+impl FrameSource for GifFrames {
+    fn resolution(&self) -> &Resolution {
+        &self.resolution
+    }
+}
+
+/// Map disposal-method of a GIF-frame to its compositor-equivalent.
+fn to_disposal(method: DisposalMethod) -> Disposal {
+    match method {
+        DisposalMethod::Keep => Disposal::KEEP,
+        DisposalMethod::Background => Disposal::BACKGROUND,
+        DisposalMethod::Previous => Disposal::PREVIOUS,
+        _ => Disposal::NONE,
+    }
+}
+
+/// Converts the indexed pixels of a GIF-frame into an RGBA-patch.
+fn to_patch(frame: &gift::block::Frame, global_color_table: Option<&[u8]>) -> Patch {
+    let desc = &frame.image_desc;
+    let width = desc.width() as usize;
+    let height = desc.height() as usize;
+
+    let colors = match &frame.local_color_table {
+        Some(table) => table.colors(),
+        None => global_color_table.unwrap_or(&[]),
+    };
+
+    let (disposal, transparent_index) = match &frame.graphic_control_ext {
+        Some(gce) => (to_disposal(gce.disposal_method()), gce.transparent_color()),
+        None => (Disposal::NONE, None),
+    };
+
+    let indices = frame.image_data.data();
+    let mut rgba: Vec<u8> = vec![0; width * height * 4];
+
+    for (stored_row, target_row) in row_order(height, desc.interlaced()).enumerate() {
+        for col in 0..width {
+            let index = match indices.get(stored_row * width + col) {
+                Some(index) => *index,
+                None => continue, // Truncated frame, keep transparent
+            };
+
+            if transparent_index == Some(index) {
+                continue;
+            }
+
+            let color = index as usize * 3;
+            if color + 3 > colors.len() {
+                continue;
+            }
+
+            let pixel = (target_row * width + col) * 4;
+            rgba[pixel..pixel + 3].copy_from_slice(&colors[color..color + 3]);
+            rgba[pixel + 3] = 255;
+        }
+    }
+
+    Patch {
+        left: desc.left() as u32,
+        top: desc.top() as u32,
+        width: width as u32,
+        height: height as u32,
+        rgba,
+        disposal,
+    }
+}
+
+/// Rows of an image in the order they are stored. Interlaced GIFs store every
+/// 8th row first, then every 8th row offset by 4, every 4th offset by 2 and
+/// last every odd row.
+fn row_order(height: usize, interlaced: bool) -> Box<dyn Iterator<Item = usize>> {
+    if !interlaced {
+        return Box::new(0..height);
+    }
+
+    Box::new(
+        (0..height)
+            .step_by(8)
+            .chain((4..height).step_by(8))
+            .chain((2..height).step_by(4))
+            .chain((1..height).step_by(2)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::row_order;
+    use super::Animation;
+    use super::GifFrames;
+    use super::{detect_format, open_frames, FileFormat, FrameSource};
+    use crate::position::Resolution;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+    #[test]
+    fn when_gif_is_opened_then_resolution_is_size_of_canvas() {
+        let frames = GifFrames::open("tests/samples/sample-disposal-previous.gif").unwrap();
+        assert_eq!(frames.resolution(), &Resolution::new(4, 4));
+    }
+
+    #[test]
+    fn when_file_does_not_exist_then_fail() {
+        assert!(GifFrames::open("tests/samples/does-not-exist.gif").is_err());
+    }
+
+    #[test]
+    fn when_file_is_not_a_gif_then_fail() {
+        assert!(GifFrames::open("tests/samples/run-samples.sh").is_err());
+    }
+
+    #[test]
+    fn when_disposal_is_background_then_clear_area_of_previous_frame() {
+        let frames = _decode("tests/samples/sample-disposal-background.gif");
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(_pixel(&frames[0], 0, 0), RED);
+        assert_eq!(_pixel(&frames[1], 0, 0), TRANSPARENT);
+        assert_eq!(_pixel(&frames[1], 1, 1), TRANSPARENT);
+        assert_eq!(_pixel(&frames[1], 2, 2), BLUE);
+        assert_eq!(_pixel(&frames[1], 3, 3), BLUE);
+    }
+
+    #[test]
+    fn when_disposal_is_previous_then_restore_area_of_previous_frame() {
+        let frames = _decode("tests/samples/sample-disposal-previous.gif");
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(_pixel(&frames[1], 0, 0), GREEN);
+        assert_eq!(_pixel(&frames[1], 2, 2), RED);
+        assert_eq!(_pixel(&frames[2], 0, 0), RED);
+        assert_eq!(_pixel(&frames[2], 1, 1), RED);
+        assert_eq!(_pixel(&frames[2], 3, 3), BLUE);
+    }
+
+    #[test]
+    fn when_disposal_is_keep_then_transparent_pixels_show_previous_frame() {
+        let frames = _decode("tests/samples/sample-disposal-keep.gif");
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(_pixel(&frames[1], 0, 0), RED);
+        assert_eq!(_pixel(&frames[1], 1, 1), BLUE);
+        assert_eq!(_pixel(&frames[1], 3, 3), RED);
+    }
+
+    #[test]
+    fn when_gif_specifies_delay_then_use_it() {
+        let delays: Vec<Option<u16>> = GifFrames::open("tests/samples/sample-disposal-keep.gif")
+            .unwrap()
+            .map(|frame| frame.unwrap().delay_cs)
+            .collect();
+
+        assert_eq!(delays, vec![Some(10), Some(20)]);
+    }
+
+    #[test]
+    fn when_animation_is_decoded_then_hold_all_frames() {
+        let source = open_frames("tests/samples/sample-disposal-previous.gif").unwrap();
+        let animation = Animation::from_source(source).unwrap();
+
+        assert_eq!(animation.resolution, Resolution::new(4, 4));
+        assert_eq!(animation.frames.len(), 3);
+        assert_eq!(_pixel(&animation.frames[2].raster, 0, 0), RED);
+        assert_eq!(_pixel(&animation.frames[2].raster, 3, 3), BLUE);
+    }
+
+    #[test]
+    fn when_delays_are_overridden_then_replace_only_given_ones() {
+        let source = open_frames("tests/samples/sample-disposal-previous.gif").unwrap();
+        let mut animation = Animation::from_source(source).unwrap();
+        let original_delay = animation.frames[2].delay_cs;
+
+        animation.override_delays(&[50, 0]);
+
+        assert_eq!(animation.frames[0].delay_cs, Some(50));
+        assert_eq!(animation.frames[1].delay_cs, Some(0));
+        assert_eq!(animation.frames[2].delay_cs, original_delay);
+    }
+
+    #[test]
+    fn when_directory_is_opened_then_read_it_as_sequence() {
+        let source = open_frames("tests/samples/sequence").unwrap();
+        assert_eq!(source.count(), 3);
+    }
+
+    #[test]
+    fn when_signature_is_known_then_detect_format() {
+        assert_eq!(detect_format(b"GIF87a\x01\x00", 64), Some(FileFormat::GIF));
+        assert_eq!(detect_format(b"GIF89a\x01\x00", 64), Some(FileFormat::GIF));
+        assert_eq!(
+            detect_format(b"\x89PNG\r\n\x1a\n\x00\x00", 64),
+            Some(FileFormat::PNG)
+        );
+        assert_eq!(
+            detect_format(b"RIFF\x00\x00\x00\x00WEBP", 64),
+            Some(FileFormat::WEBP)
+        );
+        assert_eq!(
+            detect_format(b"\xff\xd8\xff\xe0", 64),
+            Some(FileFormat::JPEG)
+        );
+        assert_eq!(
+            detect_format(
+                b"BM\x66\x00\x00\x00\x00\x00\x00\x00\x36\x00\x00\x00\x28\x00\x00\x00",
+                102
+            ),
+            Some(FileFormat::BMP)
+        );
+    }
+
+    #[test]
+    fn when_signature_is_unknown_then_detect_nothing() {
+        assert_eq!(detect_format(b"", 64), None);
+        assert_eq!(detect_format(b"GIF", 64), None);
+        assert_eq!(detect_format(b"RIFF\x00\x00\x00\x00WAVE", 64), None);
+    }
+
+    #[test]
+    fn when_bm_is_not_followed_by_bmp_header_then_detect_nothing() {
+        let header = b"BM\x66\x00\x00\x00\x00\x00\x00\x00\x36\x00\x00\x00\x28\x00\x00\x00";
+
+        assert_eq!(detect_format(b"BM plain text, no image", 23), None);
+        assert_eq!(detect_format(header, 101), None, "Larger than the file");
+        assert_eq!(detect_format(&header[..14], 102), None);
+
+        let mut unknown_dib = header.to_vec();
+        unknown_dib[14] = 0x29;
+        assert_eq!(detect_format(&unknown_dib, 102), None);
+    }
+
+    #[test]
+    fn when_bmp_header_states_no_or_smaller_size_then_detect_bmp() {
+        let header = b"BM\x00\x00\x00\x00\x00\x00\x00\x00\x36\x00\x00\x00\x28\x00\x00\x00";
+        assert_eq!(detect_format(header, 102), Some(FileFormat::BMP));
+
+        let padded = b"BM\x66\x00\x00\x00\x00\x00\x00\x00\x36\x00\x00\x00\x28\x00\x00\x00";
+        assert_eq!(detect_format(padded, 104), Some(FileFormat::BMP));
+
+        let os2_v2 = b"BM\x66\x00\x00\x00\x00\x00\x00\x00\x4e\x00\x00\x00\x40\x00\x00\x00";
+        assert_eq!(detect_format(os2_v2, 102), Some(FileFormat::BMP));
+
+        assert!(open_frames("tests/samples/sample-still-size-0.bmp").is_ok());
+    }
+
+    #[test]
+    fn when_file_is_opened_then_choose_decoder_by_signature() {
+        assert!(open_frames("tests/samples/sample-animated.webp").is_ok());
+        assert!(open_frames("tests/samples/sample-animated.png").is_ok());
+        assert!(open_frames("tests/samples/sample-still.jpg").is_ok());
+        assert!(open_frames("tests/samples/sample-still.bmp").is_ok());
+        assert!(open_frames("tests/samples/run-samples.sh").is_err());
+    }
+
+    #[test]
+    fn when_not_interlaced_then_rows_are_in_order() {
+        assert_eq!(row_order(4, false).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn when_interlaced_then_rows_are_in_order_of_passes() {
+        assert_eq!(
+            row_order(10, true).collect::<Vec<_>>(),
+            vec![0, 8, 4, 2, 6, 1, 3, 5, 7, 9]
+        );
+    }
+
+    fn _decode(path: &str) -> Vec<Vec<u8>> {
+        GifFrames::open(path)
+            .unwrap()
+            .map(|frame| frame.unwrap().raster)
+            .collect()
+    }
+
+    fn _pixel(raster: &[u8], x: usize, y: usize) -> [u8; 4] {
+        let i = (y * 4 + x) * 4;
+        [raster[i], raster[i + 1], raster[i + 2], raster[i + 3]]
+    }
+}
@@ -0,0 +1,262 @@
+//! Fills of the background around the GIF: Gradients and patterns of two
+//! colors, rendered as raster per screen.
+
+use crate::position::Resolution;
+
+const DEFAULT_ANGLE: f32 = 90.0;
+const DEFAULT_CELL_SIZE: u32 = 16;
+
+/// Pattern to fill the background with, instead of a single color. Colors are
+/// X11-color-names.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BackgroundFill {
+    /// Gradient along a straight line, rotated clockwise by degrees. 0 runs
+    /// from left to right, 90 from top to bottom.
+    LINEAR {
+        from: String,
+        to: String,
+        angle: f32,
+    },
+    /// Gradient from the center to the corners.
+    RADIAL { inner: String, outer: String },
+    /// Squares of given size in alternating colors, starting top-left.
+    CHECKERBOARD {
+        first: String,
+        second: String,
+        size: u32,
+    },
+}
+
+impl BackgroundFill {
+    /// Parses a fill like `LINEAR:black,navy,45`, `RADIAL:gray30,black` or
+    /// `CHECKERBOARD:gray80,white,8`. Angle and size are optional. `None`, if
+    /// not valid.
+    pub fn parse(value: &str) -> Option<BackgroundFill> {
+        let (kind, params) = value.split_at(value.find(':')?);
+        let params: Vec<&str> = params[1..].split(',').map(|param| param.trim()).collect();
+
+        if params.len() < 2 || params[0].is_empty() || params[1].is_empty() {
+            return None;
+        }
+
+        let (first, second) = (params[0].to_string(), params[1].to_string());
+
+        match (kind, params.len()) {
+            ("LINEAR", 2) => Some(BackgroundFill::LINEAR {
+                from: first,
+                to: second,
+                angle: DEFAULT_ANGLE,
+            }),
+            ("LINEAR", 3) => Some(BackgroundFill::LINEAR {
+                from: first,
+                to: second,
+                angle: params[2]
+                    .parse()
+                    .ok()
+                    .filter(|angle: &f32| angle.is_finite())?,
+            }),
+            ("RADIAL", 2) => Some(BackgroundFill::RADIAL {
+                inner: first,
+                outer: second,
+            }),
+            ("CHECKERBOARD", 2) => Some(BackgroundFill::CHECKERBOARD {
+                first,
+                second,
+                size: DEFAULT_CELL_SIZE,
+            }),
+            ("CHECKERBOARD", 3) => Some(BackgroundFill::CHECKERBOARD {
+                first,
+                second,
+                size: params[2].parse().ok().filter(|size| *size > 0)?,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Names of both colors, in order of the fill.
+    pub fn colors(&self) -> (&str, &str) {
+        match self {
+            BackgroundFill::LINEAR { from, to, .. } => (from, to),
+            BackgroundFill::RADIAL { inner, outer } => (inner, outer),
+            BackgroundFill::CHECKERBOARD { first, second, .. } => (first, second),
+        }
+    }
+}
+
+/// Renders the fill as opaque RGBA-raster in given resolution. `colors` are
+/// the RGBA-values of the colors of the fill, in its order.
+pub fn render(fill: &BackgroundFill, colors: &[[u8; 4]; 2], resolution: &Resolution) -> Vec<u8> {
+    let width = resolution.width as usize;
+    let height = resolution.height as usize;
+
+    let mut raster: Vec<u8> = Vec::with_capacity(width * height * 4);
+
+    // Positions are relative to the center of the screen
+    let center_x = width as f32 / 2.0;
+    let center_y = height as f32 / 2.0;
+
+    for y in 0..height {
+        for x in 0..width {
+            let dx = x as f32 + 0.5 - center_x;
+            let dy = y as f32 + 0.5 - center_y;
+
+            let pixel = match fill {
+                BackgroundFill::LINEAR { angle, .. } => {
+                    let (sin, cos) = angle.to_radians().sin_cos();
+                    let extent = center_x * cos.abs() + center_y * sin.abs();
+
+                    mix(colors, 0.5 + (dx * cos + dy * sin) / (2.0 * extent))
+                }
+                BackgroundFill::RADIAL { .. } => {
+                    let extent = center_x.hypot(center_y);
+
+                    mix(colors, dx.hypot(dy) / extent)
+                }
+                BackgroundFill::CHECKERBOARD { size, .. } => {
+                    let cell = x / *size as usize + y / *size as usize;
+
+                    colors[cell % 2]
+                }
+            };
+
+            raster.extend_from_slice(&pixel);
+        }
+    }
+
+    raster
+}
+
+/// Mixes both colors by ratio of the second, which is clamped to 0 and 1.
+fn mix(colors: &[[u8; 4]; 2], ratio: f32) -> [u8; 4] {
+    let ratio = ratio.max(0.0).min(1.0);
+    let mut mixed = [255; 4];
+
+    for index in 0..3 {
+        let first = colors[0][index] as f32;
+        let second = colors[1][index] as f32;
+
+        mixed[index] = (first + (second - first) * ratio).round() as u8;
+    }
+
+    mixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, BackgroundFill};
+    use crate::position::Resolution;
+
+    const COLORS: [[u8; 4]; 2] = [[0, 0, 0, 255], [200, 100, 50, 255]];
+
+    #[test]
+    fn when_fill_is_linear_then_parse_colors_and_angle() {
+        assert_eq!(
+            BackgroundFill::parse("LINEAR:black,#203040,45"),
+            Some(BackgroundFill::LINEAR {
+                from: "black".to_string(),
+                to: "#203040".to_string(),
+                angle: 45.0
+            })
+        );
+
+        assert_eq!(
+            BackgroundFill::parse("LINEAR:black,navy"),
+            Some(BackgroundFill::LINEAR {
+                from: "black".to_string(),
+                to: "navy".to_string(),
+                angle: 90.0
+            })
+        );
+    }
+
+    #[test]
+    fn when_fill_is_radial_or_checkerboard_then_parse_it() {
+        assert_eq!(
+            BackgroundFill::parse("RADIAL:gray30,black"),
+            Some(BackgroundFill::RADIAL {
+                inner: "gray30".to_string(),
+                outer: "black".to_string()
+            })
+        );
+
+        assert_eq!(
+            BackgroundFill::parse("CHECKERBOARD:gray80,white,8"),
+            Some(BackgroundFill::CHECKERBOARD {
+                first: "gray80".to_string(),
+                second: "white".to_string(),
+                size: 8
+            })
+        );
+    }
+
+    #[test]
+    fn when_fill_is_invalid_then_parse_none() {
+        assert_eq!(BackgroundFill::parse("LINEAR"), None);
+        assert_eq!(BackgroundFill::parse("LINEAR:black"), None);
+        assert_eq!(BackgroundFill::parse("LINEAR:black,,90"), None);
+        assert_eq!(BackgroundFill::parse("LINEAR:black,white,up"), None);
+        assert_eq!(BackgroundFill::parse("RADIAL:black,white,10"), None);
+        assert_eq!(BackgroundFill::parse("CHECKERBOARD:black,white,0"), None);
+        assert_eq!(BackgroundFill::parse("STRIPES:black,white"), None);
+    }
+
+    #[test]
+    fn when_linear_fill_runs_left_to_right_then_mix_by_column() {
+        let fill = BackgroundFill::parse("LINEAR:a,b,0").unwrap();
+        let raster = render(&fill, &COLORS, &Resolution::new(4, 2));
+
+        assert_eq!(
+            _red(&raster),
+            vec![25, 75, 125, 175, 25, 75, 125, 175],
+            "Each column is mixed by its center"
+        );
+    }
+
+    #[test]
+    fn when_linear_fill_runs_top_to_bottom_then_mix_by_row() {
+        let fill = BackgroundFill::parse("LINEAR:a,b").unwrap();
+        let raster = render(&fill, &COLORS, &Resolution::new(2, 2));
+
+        assert_eq!(_red(&raster), vec![50, 50, 150, 150]);
+    }
+
+    #[test]
+    fn when_fill_is_radial_then_mix_by_distance_to_center() {
+        let fill = BackgroundFill::parse("RADIAL:a,b").unwrap();
+        let raster = render(&fill, &COLORS, &Resolution::new(3, 3));
+
+        let red = _red(&raster);
+
+        assert_eq!(red[4], 0, "Center has inner color");
+        assert_eq!(red[0], red[8], "Corners are equal");
+        assert!(red[1] < red[0], "Edges are closer than corners");
+    }
+
+    #[test]
+    fn when_fill_is_checkerboard_then_alternate_cells() {
+        let fill = BackgroundFill::parse("CHECKERBOARD:a,b,2").unwrap();
+        let raster = render(&fill, &COLORS, &Resolution::new(4, 3));
+
+        assert_eq!(
+            _red(&raster),
+            vec![
+                0, 0, 200, 200, //
+                0, 0, 200, 200, //
+                200, 200, 0, 0,
+            ]
+        );
+    }
+
+    #[test]
+    fn when_fill_is_rendered_then_pixels_are_opaque() {
+        let fill = BackgroundFill::parse("LINEAR:a,b,30").unwrap();
+        let raster = render(&fill, &COLORS, &Resolution::new(5, 7));
+
+        assert_eq!(raster.len(), 5 * 7 * 4);
+        assert!(raster.chunks_exact(4).all(|pixel| pixel[3] == 255));
+    }
+
+    fn _red(raster: &[u8]) -> Vec<u8> {
+        raster.chunks_exact(4).map(|pixel| pixel[0]).collect()
+    }
+}
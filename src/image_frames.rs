@@ -0,0 +1,233 @@
+//! Decodes PNG-, WebP-, JPEG- and BMP-files into composed, full-size frames.
+//! Still images are decoded as a single frame.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::iter;
+
+use image::codecs::bmp::BmpDecoder;
+use image::codecs::jpeg::JpegDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Delay, DynamicImage, ImageDecoder};
+
+use crate::decoder::{ComposedFrame, FrameSource};
+use crate::position::Resolution;
+
+/// Iterator over the frames of an image, decoded by `image`. Frames of APNG
+/// and animated WebP are composed on the canvas by `image` already.
+pub struct ImageFrames {
+    frames: image::Frames<'static>,
+    resolution: Resolution,
+}
+
+impl ImageFrames {
+    /// Opens an animated or still PNG.
+    pub fn open_png(path: &str) -> Result<ImageFrames, String> {
+        let decoder = PngDecoder::new(open_file(path)?)
+            .map_err(|e| format!("File {} is not a valid PNG: {}", path, e))?;
+
+        let (width, height) = decoder.dimensions();
+
+        let frames = if decoder.is_apng() {
+            decoder.apng().into_frames()
+        } else {
+            still_frame(decoder)
+        };
+
+        Ok(ImageFrames {
+            frames,
+            resolution: Resolution::new(width, height),
+        })
+    }
+
+    /// Opens an animated or still WebP.
+    pub fn open_webp(path: &str) -> Result<ImageFrames, String> {
+        let decoder = WebPDecoder::new(open_file(path)?)
+            .map_err(|e| format!("File {} is not a valid WebP: {}", path, e))?;
+
+        let (width, height) = decoder.dimensions();
+
+        let frames = if decoder.has_animation() {
+            decoder.into_frames()
+        } else {
+            still_frame(decoder)
+        };
+
+        Ok(ImageFrames {
+            frames,
+            resolution: Resolution::new(width, height),
+        })
+    }
+
+    /// Opens a JPEG as single frame.
+    pub fn open_jpeg(path: &str) -> Result<ImageFrames, String> {
+        let decoder = JpegDecoder::new(open_file(path)?)
+            .map_err(|e| format!("File {} is not a valid JPEG: {}", path, e))?;
+
+        let (width, height) = decoder.dimensions();
+
+        Ok(ImageFrames {
+            frames: still_frame(decoder),
+            resolution: Resolution::new(width, height),
+        })
+    }
+
+    /// Opens a BMP as single frame.
+    pub fn open_bmp(path: &str) -> Result<ImageFrames, String> {
+        let decoder = BmpDecoder::new(open_file(path)?)
+            .map_err(|e| format!("File {} is not a valid BMP: {}", path, e))?;
+
+        let (width, height) = decoder.dimensions();
+
+        Ok(ImageFrames {
+            frames: still_frame(decoder),
+            resolution: Resolution::new(width, height),
+        })
+    }
+}
+
+impl Iterator for ImageFrames {
+    type Item = Result<ComposedFrame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = match self.frames.next()? {
+            Ok(frame) => frame,
+            Err(e) => return Some(Err(format!("Unable to decode frame: {}", e))),
+        };
+
+        let delay_cs = to_centiseconds(frame.delay());
+
+        Some(Ok(ComposedFrame {
+            raster: frame.into_buffer().into_raw(),
+            delay_cs: Some(delay_cs),
+        }))
+    }
+}
+
+impl FrameSource for ImageFrames {
+    fn resolution(&self) -> &Resolution {
+        &self.resolution
+    }
+}
+
+fn open_file(path: &str) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("Unable to read file {}: {}", path, e))
+}
+
+/// Single frame of a still image. Decoding is deferred until the frame is
+/// requested, like for animations.
+fn still_frame<D: ImageDecoder<'static> + 'static>(decoder: D) -> image::Frames<'static> {
+    image::Frames::new(Box::new(iter::once_with(move || {
+        DynamicImage::from_decoder(decoder).map(|image| image::Frame::new(image.into_rgba8()))
+    })))
+}
+
+/// Round delay of a frame to centiseconds, like GIF specifies it.
+fn to_centiseconds(delay: Delay) -> u16 {
+    let (numer, denom) = delay.numer_denom_ms();
+    let centiseconds = (numer as u64 + 5 * denom as u64) / (10 * denom.max(1) as u64);
+
+    centiseconds.min(u16::MAX as u64) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_centiseconds;
+    use super::ImageFrames;
+    use crate::decoder::FrameSource;
+    use crate::position::Resolution;
+    use image::Delay;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn when_apng_is_opened_then_compose_frames_with_delays() {
+        let frames = ImageFrames::open_png("tests/samples/sample-animated.png").unwrap();
+        _assert_sample(frames);
+    }
+
+    #[test]
+    fn when_webp_is_opened_then_compose_frames_with_delays() {
+        let frames = ImageFrames::open_webp("tests/samples/sample-animated.webp").unwrap();
+        _assert_sample(frames);
+    }
+
+    #[test]
+    fn when_still_png_is_opened_then_decode_single_frame() {
+        let frames = ImageFrames::open_png("tests/samples/sample-still.png").unwrap();
+        assert_eq!(frames.resolution(), &Resolution::new(4, 4));
+
+        let frames: Vec<_> = frames.map(|frame| frame.unwrap()).collect();
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(_pixel(&frames[0].raster, 0, 0), RED);
+        assert_eq!(_pixel(&frames[0].raster, 3, 3), BLUE);
+    }
+
+    #[test]
+    fn when_bmp_is_opened_then_decode_single_frame() {
+        let frames = ImageFrames::open_bmp("tests/samples/sample-still.bmp").unwrap();
+        assert_eq!(frames.resolution(), &Resolution::new(4, 4));
+
+        let frames: Vec<_> = frames.map(|frame| frame.unwrap()).collect();
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(_pixel(&frames[0].raster, 0, 0), RED);
+        assert_eq!(_pixel(&frames[0].raster, 3, 3), BLUE);
+    }
+
+    #[test]
+    fn when_jpeg_is_opened_then_decode_single_frame() {
+        let frames = ImageFrames::open_jpeg("tests/samples/sample-still.jpg").unwrap();
+        assert_eq!(frames.resolution(), &Resolution::new(4, 4));
+
+        let frames: Vec<_> = frames.map(|frame| frame.unwrap()).collect();
+
+        // Lossy, so only check the size of the raster
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].raster.len(), 4 * 4 * 4);
+    }
+
+    #[test]
+    fn when_file_is_no_png_then_fail() {
+        assert!(ImageFrames::open_png("tests/samples/sample-animated.webp").is_err());
+    }
+
+    #[test]
+    fn when_file_is_no_webp_then_fail() {
+        assert!(ImageFrames::open_webp("tests/samples/sample-animated.png").is_err());
+    }
+
+    #[test]
+    fn when_delay_is_in_milliseconds_then_round_to_centiseconds() {
+        assert_eq!(to_centiseconds(Delay::from_numer_denom_ms(100, 1)), 10);
+        assert_eq!(to_centiseconds(Delay::from_numer_denom_ms(1000, 30)), 3);
+        assert_eq!(to_centiseconds(Delay::from_numer_denom_ms(4, 1)), 0);
+        assert_eq!(
+            to_centiseconds(Delay::from_numer_denom_ms(u32::MAX, 1)),
+            u16::MAX
+        );
+    }
+
+    fn _assert_sample(frames: ImageFrames) {
+        assert_eq!(frames.resolution(), &Resolution::new(4, 4));
+
+        let frames: Vec<_> = frames.map(|frame| frame.unwrap()).collect();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].delay_cs, Some(10));
+        assert_eq!(frames[1].delay_cs, Some(20));
+        assert_eq!(_pixel(&frames[0].raster, 3, 3), RED);
+        assert_eq!(_pixel(&frames[1].raster, 0, 0), RED);
+        assert_eq!(_pixel(&frames[1].raster, 3, 3), BLUE);
+    }
+
+    fn _pixel(raster: &[u8], x: usize, y: usize) -> [u8; 4] {
+        let i = (y * 4 + x) * 4;
+        [raster[i], raster[i + 1], raster[i + 2], raster[i + 3]]
+    }
+}
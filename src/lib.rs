@@ -1,402 +1,1224 @@
 #[macro_use]
 pub mod macros;
 
+mod backdrop;
+mod cache;
+mod compositor;
+pub mod control;
+mod decoder;
+mod fill;
+mod image_frames;
 pub mod options;
+mod pixel_art;
+pub mod playlist;
+mod pool;
 mod position;
+mod raster;
+mod resampling;
+mod scheduler;
 pub mod screens;
+mod sequence;
 mod shm;
+mod stream;
+mod transition;
+pub mod watch;
 mod xatoms;
 pub mod xcontext;
 
-use pix::rgb::Rgba8;
-
 use std::collections::HashMap;
 use std::ffi::c_void;
-use std::fs::File;
-use std::os::raw::{c_uchar, c_uint};
-use std::rc::Rc;
+use std::io::Write;
+use std::os::raw::c_uint;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
 use std::sync::Arc;
-use std::{thread, time};
+use std::thread::{self, ScopedJoinHandle};
+use std::time::{self, Instant};
 
 use x11::xlib::*;
 
+use backdrop::{BackdropPosition, BackgroundImage};
+use cache::Cache;
+use control::{Command, Request};
+use decoder::{Animation, ComposedFrame, FrameSource};
+use fill::BackgroundFill;
 use options::Options;
+use playlist::Playlist;
 use position::*;
+use scheduler::{Playback, Timeline};
 use screens::*;
 use shm::*;
+use stream::{FrameStream, StreamedFrames};
+use transition::Transition;
+use watch::FileWatcher;
 use xatoms::*;
 use xcontext::XContext;
 
-const EXIT_INVALID_FILE: i32 = 103;
+pub const EXIT_INVALID_FILE: i32 = 103;
 
 const VERSION: &str = "0.3.2";
 
-/// Screens to render wallpapers on, with needed resolution. And the pre-
-/// rendered frames in a seperate map.
+/// Time between the steps of transitions, if no frame is due earlier.
+const TRANSITION_INTERVAL: time::Duration = time::Duration::from_millis(20);
+
+/// Screens to render wallpapers on, with needed frames. And the rendered
+/// frames in a seperate map.
 pub struct Wallpapers {
+    /// All screens, also those without a visible wallpaper, to paint the
+    /// background on.
+    all_screens: Vec<screens::Screen>,
     screens: Vec<WallpaperOnScreen>,
-    frames_by_resolution: HashMap<Resolution, Vec<Frame>>,
+    frame_sets: HashMap<FrameSetKey, FrameSet>,
+    /// Blends of the previous content into the background, of screens
+    /// without a visible wallpaper.
+    background_transitions: Vec<(screens::Screen, TransitionOnScreen)>,
+}
+
+/// Wallpapers rendered as plain rasters, not yet uploaded into shared memory.
+/// So they can be rendered in a background-thread, without using the
+/// X-connection.
+struct RenderedWallpapers {
+    all_screens: Vec<screens::Screen>,
+    screens: Vec<(screens::Screen, Vec<ImagePlacement>, FrameSetKey)>,
+    frame_sets: HashMap<FrameSetKey, RenderedFrames>,
+}
+
+impl Wallpapers {
+    /// Restarts the timelines of all screens with their first frame at
+    /// `start`.
+    fn restart(&mut self, start: Instant) {
+        for screen in self.screens.iter_mut() {
+            screen.timeline = Timeline::new(self.frame_sets[&screen.frame_set].len(), start);
+        }
+    }
+
+    /// Loops of the least played animation. `None`, if all screens show still
+    /// images.
+    fn least_loops(&self) -> Option<u32> {
+        self.screens
+            .iter()
+            .filter(|screen| self.frame_sets[&screen.frame_set].len() > 1)
+            .map(|screen| screen.timeline.loops())
+            .min()
+    }
 }
 
-/// Resolution and placement of a wallpaper on a screen.
+/// Wallpapers of another file, rendered in the background while animating.
+struct Upcoming<'scope> {
+    options: Arc<Options>,
+    rendering: ScopedJoinHandle<'scope, Result<RenderedWallpapers, String>>,
+    /// Requested by command and shown once rendered. Otherwise the next file
+    /// of the playlist, shown when the current one was played long enough.
+    is_swap: bool,
+    /// Its files changed while rendering, so it is rendered again.
+    is_stale: bool,
+}
+
+/// Placements of a wallpaper on a screen, its frames and its own timeline.
+/// Tiled wallpapers have a placement per visible tile, all showing the same
+/// frame.
 struct WallpaperOnScreen {
-    placement: ImagePlacement,
+    placements: Vec<ImagePlacement>,
+    frame_set: FrameSetKey,
+    timeline: Timeline,
+    screen: screens::Screen,
+    /// Blend of the previous content of the screen into this wallpaper.
+    transition: Option<TransitionOnScreen>,
+}
+
+/// Blends the previous content of a screen into the frames of its wallpaper,
+/// in a screen-sized image in shared memory.
+struct TransitionOnScreen {
+    transition: Transition,
+    duration: time::Duration,
+    start: Instant,
+    /// Content of the screen before, in the byte-order of XImages.
+    outgoing: Vec<u8>,
+    /// Content of the screen after: The backdrop with the frame shown now
+    /// painted over it. Frames cover the same placements each time, so the
+    /// rest of the backdrop is kept.
+    incoming: Vec<u8>,
+    /// Frame of the wallpaper shown now.
+    frame_index: usize,
+    /// Holds the outgoing content or the last blend of it.
+    ximage: Box<XImage>,
+    xshminfo: Box<x11::xshm::XShmSegmentInfo>, // Must exist as long as ximage
+}
+
+impl TransitionOnScreen {
+    /// Starts a transition from the current content of the screen on the
+    /// pixmap. It is also the backdrop, until captured again.
+    fn start(
+        xcontext: &Box<XContext>,
+        screen: &screens::Screen,
+        options: &Options,
+        start: Instant,
+    ) -> Option<TransitionOnScreen> {
+        let transition = options.transition?;
+        let (ximage, xshminfo) =
+            create_shared_image(xcontext, &Resolution::new(screen.width, screen.height));
+
+        let mut started = TransitionOnScreen {
+            transition,
+            duration: options.transition_duration,
+            start,
+            outgoing: Vec::new(),
+            incoming: Vec::new(),
+            frame_index: 0,
+            ximage,
+            xshminfo,
+        };
+
+        started.capture(xcontext, screen);
+        started.outgoing = unsafe {
+            std::slice::from_raw_parts(started.xshminfo.shmaddr as *const u8, started.size())
+        }
+        .to_vec();
+        started.incoming = started.outgoing.clone();
+
+        Some(started)
+    }
+
+    /// Captures the content of the screen on the pixmap as the backdrop to
+    /// draw frames over.
+    fn capture_backdrop(&mut self, xcontext: &Box<XContext>, screen: &screens::Screen) {
+        self.capture(xcontext, screen);
+
+        let size = self.size();
+        let captured =
+            unsafe { std::slice::from_raw_parts(self.xshminfo.shmaddr as *const u8, size) };
+        self.incoming.copy_from_slice(captured);
+
+        // Blends start from the outgoing content
+        unsafe { std::slice::from_raw_parts_mut(self.xshminfo.shmaddr as *mut u8, size) }
+            .copy_from_slice(&self.outgoing);
+    }
+
+    /// Copies the content of the screen on the pixmap into shared memory.
+    fn capture(&mut self, xcontext: &Box<XContext>, screen: &screens::Screen) {
+        unsafe {
+            x11::xshm::XShmGetImage(
+                xcontext.display,
+                xcontext.pixmap,
+                &mut *self.ximage,
+                screen.x_org,
+                screen.y_org,
+                XAllPlanes() as c_uint,
+            );
+        }
+    }
+
+    /// Paints the frame at its placements over the backdrop.
+    fn paint_frame(
+        &mut self,
+        screen: &screens::Screen,
+        placements: &[ImagePlacement],
+        frame: &XImage,
+    ) {
+        let resolution = Resolution::new(screen.width, screen.height);
+        let frame_resolution = Resolution::new(frame.width as u32, frame.height as u32);
+
+        let frame_raster = unsafe {
+            std::slice::from_raw_parts(
+                frame.data as *const u8,
+                (frame_resolution.width * frame_resolution.height * 4) as usize,
+            )
+        };
+
+        for placement in placements {
+            let on_screen = ImagePlacement::new(
+                placement.src_x,
+                placement.src_y,
+                placement.dest_x - screen.x_org,
+                placement.dest_y - screen.y_org,
+                placement.width,
+                placement.height,
+            );
+
+            raster::paint(
+                frame_raster,
+                &frame_resolution,
+                &mut self.incoming,
+                &resolution,
+                &on_screen,
+            );
+        }
+    }
+
+    /// Blends the outgoing content into the incoming one by the progress at
+    /// `now` and puts it onto the screen of the pixmap. Returns `true`, if the
+    /// transition is done.
+    fn draw(&mut self, xcontext: &Box<XContext>, screen: &screens::Screen, now: Instant) -> bool {
+        let resolution = Resolution::new(screen.width, screen.height);
+
+        let progress = if self.duration.is_zero() {
+            1.0
+        } else {
+            now.saturating_duration_since(self.start).as_secs_f32() / self.duration.as_secs_f32()
+        };
+
+        let blended = unsafe {
+            std::slice::from_raw_parts_mut(self.xshminfo.shmaddr as *mut u8, self.size())
+        };
+
+        transition::blend(
+            self.transition,
+            &self.outgoing,
+            &self.incoming,
+            &resolution,
+            progress,
+            blended,
+        );
+
+        unsafe {
+            x11::xshm::XShmPutImage(
+                xcontext.display,
+                xcontext.pixmap,
+                xcontext.gc,
+                &mut *self.ximage,
+                0,
+                0,
+                screen.x_org,
+                screen.y_org,
+                screen.width,
+                screen.height,
+                False,
+            );
+        }
+
+        progress >= 1.0
+    }
+
+    fn size(&self) -> usize {
+        (self.ximage.width * self.ximage.height * 4) as usize
+    }
+
+    /// Frees the shared memory of the image.
+    fn free(&mut self, display: *mut Display) {
+        unsafe { x11::xshm::XShmDetach(display, self.xshminfo.as_mut() as *mut _) };
+        destroy_xshm_sgmnt_inf(&mut self.xshminfo);
+    }
+}
+
+/// Identifies the frames of a file, rendered in a specific resolution, over
+/// the part of the background-image at their position, if any.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct FrameSetKey {
+    path: String,
     resolution: Resolution,
-    _screen: screens::Screen, // TODO Check if useful at some time
+    backdrop: Option<BackdropPosition>,
 }
 
 /// Combines x-structs, raster- and metadata for a singe frame.
 struct Frame {
     delay: time::Duration,
-    raster: Rc<Vec<c_uchar>>,
     ximage: Box<XImage>,
     xshminfo: Box<x11::xshm::XShmSegmentInfo>, // Must exist as long as ximage
 }
 
-/// Pre-render wallpaper-frames for all needed resolutions, determined by
-/// actual screens, options and image-data
+/// Raster of a single frame in the byte-order of XImages, with its delay.
+struct RenderedFrame {
+    delay: time::Duration,
+    raster: Vec<u8>,
+}
+
+/// Frames for a single resolution, rendered as plain rasters.
+enum RenderedFrames {
+    /// All frames rendered in advance.
+    Prerendered(Vec<RenderedFrame>),
+    /// Frames rendered ahead by a background-thread.
+    Streamed(StreamedFrames),
+}
+
+/// Rendered frames for a single resolution.
+enum FrameSet {
+    /// All frames rendered in advance, each into its own shared memory segment.
+    Prerendered(Vec<Frame>),
+    /// Frames rendered ahead by a background-thread and uploaded when due.
+    Streamed(FrameStream),
+}
+
+impl FrameSet {
+    /// Number of frames in this set.
+    fn len(&self) -> usize {
+        match self {
+            FrameSet::Prerendered(frames) => frames.len(),
+            FrameSet::Streamed(stream) => stream.len(),
+        }
+    }
+
+    /// Prepares the frame at `index` to be drawn. Returns its image and delay.
+    fn frame(&mut self, index: usize) -> (&mut XImage, time::Duration) {
+        match self {
+            FrameSet::Prerendered(frames) => {
+                let frame = &mut frames[index];
+                (&mut *frame.ximage, frame.delay)
+            }
+            FrameSet::Streamed(stream) => stream.frame(index),
+        }
+    }
+
+    /// Frees the shared memory of all frames.
+    fn free(&mut self, display: *mut Display) {
+        match self {
+            FrameSet::Prerendered(frames) => {
+                for frame in frames.iter_mut() {
+                    // Don't need to call XDestroy image - heap is freed by rust-guarantees. :)
+                    unsafe { x11::xshm::XShmDetach(display, frame.xshminfo.as_mut() as *mut _) };
+                    destroy_xshm_sgmnt_inf(&mut frame.xshminfo);
+                }
+            }
+            FrameSet::Streamed(stream) => stream.stop(display),
+        }
+    }
+}
+
+/// Byte-order of pixels in an XImage and the color to paint transparent
+/// pixels with.
+#[derive(Clone, Copy, Debug)]
+struct PixelFormat {
+    rgba_indices: [usize; 4],
+    background_rgba: [u8; 4],
+}
+
+/// Pre-render wallpaper-frames for all needed files and resolutions,
+/// determined by actual screens, options and image-data. Fails, if any file
+/// is not a valid image.
 pub fn render_wallpapers(
     xcontext: &Box<XContext>,
     xscreens: Screens,
     options: Arc<Options>,
     running: Arc<AtomicBool>,
-) -> Wallpapers {
-    // Decode gif-frames into raster-steps
-    let path_to_gif = options.path_to_gif.as_str();
+) -> Result<Wallpapers, String> {
+    let rendered = render_rasters(xscreens, pixel_format(xcontext), options, running)?;
 
-    // TODO Try using only low-level frames
-    // TODO Prevent double-encoding, by re-using iterator?
-    let methods = gather_disposal_methods(path_to_gif);
+    Ok(upload_wallpapers(xcontext, rendered))
+}
 
-    // Determine image-resolution
-    let first_step_result = create_decoder(path_to_gif)
-        .into_steps()
-        .nth(0)
-        .expect("No steps decoded");
+/// Renders the frames of the wallpapers as plain rasters in given pixel-
+/// format. Doesn't use the X-connection, so it runs on any thread.
+fn render_rasters(
+    xscreens: Screens,
+    format: PixelFormat,
+    options: Arc<Options>,
+    running: Arc<AtomicBool>,
+) -> Result<RenderedWallpapers, String> {
+    // Read only the headers for now, decoding may be skipped due to the cache
+    let mut sources: Vec<(String, Box<dyn FrameSource>)> = Vec::new();
+    let mut screens_with_keys: Vec<(screens::Screen, Vec<ImagePlacement>, FrameSetKey)> =
+        Vec::new();
+
+    let paths: Vec<&str> = xscreens
+        .screens
+        .iter()
+        .enumerate()
+        .map(|(index, screen)| wallpaper_path(index, screen, &options))
+        .collect();
+
+    for (index, screen) in xscreens.screens.iter().enumerate() {
+        logln!(options, "Prepare wallpaper for {:?}", screen);
 
-    if first_step_result.is_err() {
-        eprintln!(
-            "File {} is not a valid GIF: {:?}",
-            path_to_gif,
-            first_step_result.err().unwrap()
-        );
-        std::process::exit(EXIT_INVALID_FILE);
-    }
+        let path = paths[index];
 
-    let first_step = first_step_result.unwrap();
-    let raster = first_step.raster();
-    let image_resolution = Resolution {
-        width: raster.width(),
-        height: raster.height(),
-    };
+        if !sources.iter().any(|(opened, _)| opened == path) {
+            sources.push((path.to_string(), decoder::open_frames(path)?));
+        }
 
-    // Build wallpapers by screen
-    let mut screens: Vec<WallpaperOnScreen> = Vec::new();
-    let mut frames_by_resolution: HashMap<Resolution, Vec<Frame>> = HashMap::new();
+        let (_, source) = sources.iter().find(|(opened, _)| opened == path).unwrap();
 
-    for screen in xscreens.screens {
-        logln!(options, "Prepare wallpaper for {:?}", screen);
+        // Area to place the wallpaper in. Spans all screens showing the same
+        // file, if requested. Each screen shows its slice of it then.
+        let area = if options.span {
+            let screens_with_path = xscreens
+                .screens
+                .iter()
+                .zip(&paths)
+                .filter(|(_, other)| **other == path)
+                .map(|(screen, _)| screen);
 
-        // Gather target-resolution and image-placement for particular screen
-        let screen_resolution = Resolution {
-            width: screen.width,
-            height: screen.height,
+            bounding_box(screens_with_path).unwrap()
+        } else {
+            screen.clone()
         };
 
-        let target_resolution =
-            image_resolution.fit_to_screen(&screen_resolution, &options.scaling);
+        // Gather target-resolution and image-placement for particular screen
+        let area_resolution = Resolution {
+            width: area.width,
+            height: area.height,
+        };
 
-        let wallpaper_on_screen = WallpaperOnScreen {
-            placement: target_resolution.position_on_screen(&screen, Alignment::CENTER),
-            resolution: target_resolution.clone(),
-            _screen: screen.clone(),
+        let image_resolution = match cropped_resolution(source.resolution(), &options) {
+            Some(resolution) => resolution,
+            None => {
+                return Err(format!(
+                    "Crop-region {:?} lies outside of {} with {:?}",
+                    options.crop.unwrap(),
+                    path,
+                    source.resolution()
+                ));
+            }
         };
 
-        // If frames were not already rendered for given resolution, do so
-        if !frames_by_resolution.contains_key(&target_resolution) {
-            frames_by_resolution.insert(
-                target_resolution,
-                render_frames(
-                    xcontext,
-                    &wallpaper_on_screen,
-                    create_decoder(path_to_gif).into_steps().by_ref(),
-                    &methods,
-                    options.clone(),
-                    running.clone(),
-                ),
-            );
+        let target_resolution = image_resolution.fit_to_target(
+            &area_resolution,
+            &options.scaling,
+            &options.target_size,
+        );
+
+        let placements: Vec<ImagePlacement> = if options.scaling.is_tiled() {
+            target_resolution.tile_on_screen(&area, options.alignment, options.offset)
         } else {
-            logln!(
-                options,
-                "Reuse already rendered frames for {:?}",
-                target_resolution
-            );
+            vec![target_resolution.position_on_screen(&area, options.alignment, options.offset)]
+        }
+        .iter()
+        .filter_map(|placement| placement.clip_to_screen(screen))
+        .collect();
+
+        if placements.is_empty() {
+            logln!(options, "Wallpaper is not visible on {:?}", screen);
+            continue;
+        }
+
+        // Tiles share their frames, so all show the backdrop of the first one
+        let backdrop = options.background_image.as_ref().map(|_| {
+            let background_area = background_area(screen, &xscreens.screens, &options);
+            BackdropPosition::of(&placements[0], &background_area)
+        });
+
+        let key = FrameSetKey {
+            path: path.to_string(),
+            resolution: target_resolution,
+            backdrop,
+        };
+
+        if screens_with_keys.iter().any(|(_, _, other)| other == &key) {
+            logln!(options, "Reuse already rendered frames for {:?}", key);
+        }
+
+        screens_with_keys.push((screen.clone(), placements, key));
+    }
+
+    let background = match &options.background_image {
+        Some(path) => Some(BackgroundImage::open(path)?),
+        None => None,
+    };
+
+    // Clear only once, before any file stores its frames
+    if options.clear_cache {
+        logln!(options, "Clear cache");
+
+        if let Err(e) = cache::clear() {
+            eprintln!("{}", e);
+        }
+    }
+
+    // Files share the memory-budget equally
+    let memory_budget = options
+        .memory_budget
+        .map(|budget| budget / sources.len().max(1) as u64);
+
+    let mut frame_sets: HashMap<FrameSetKey, RenderedFrames> = HashMap::new();
+
+    for (path, source) in sources {
+        // Render frames only once per resolution and backdrop
+        let mut keys: Vec<FrameSetKey> = Vec::new();
+
+        for (_, _, key) in &screens_with_keys {
+            if key.path == path && !keys.contains(key) {
+                keys.push(key.clone());
+            }
         }
 
-        screens.push(wallpaper_on_screen);
+        let frames_by_key = render_frame_sets(
+            &path,
+            source,
+            keys,
+            background.as_ref(),
+            &format,
+            memory_budget,
+            options.clone(),
+            running.clone(),
+        )?;
+
+        frame_sets.extend(frames_by_key);
     }
 
+    Ok(RenderedWallpapers {
+        all_screens: xscreens.screens,
+        screens: screens_with_keys,
+        frame_sets,
+    })
+}
+
+/// Uploads the rendered frames into shared memory, to draw them onto the
+/// screens. Must be called by the thread drawing them.
+fn upload_wallpapers(xcontext: &Box<XContext>, rendered: RenderedWallpapers) -> Wallpapers {
+    let frame_sets: HashMap<FrameSetKey, FrameSet> = rendered
+        .frame_sets
+        .into_iter()
+        .map(|(key, frames)| {
+            let uploaded = match frames {
+                RenderedFrames::Prerendered(frames) => FrameSet::Prerendered(
+                    frames
+                        .into_iter()
+                        .map(|frame| {
+                            let (ximage, xshminfo) = create_shared_image(xcontext, &key.resolution);
+                            upload_raster(&xshminfo, &frame.raster);
+
+                            Frame {
+                                delay: frame.delay,
+                                ximage,
+                                xshminfo,
+                            }
+                        })
+                        .collect(),
+                ),
+                RenderedFrames::Streamed(frames) => {
+                    FrameSet::Streamed(FrameStream::new(xcontext, frames))
+                }
+            };
+
+            (key, uploaded)
+        })
+        .collect();
+
+    let start = Instant::now();
+
+    let screens = rendered
+        .screens
+        .into_iter()
+        .map(|(screen, placements, key)| WallpaperOnScreen {
+            placements,
+            timeline: Timeline::new(frame_sets[&key].len(), start),
+            frame_set: key,
+            screen,
+            transition: None,
+        })
+        .collect();
+
     Wallpapers {
+        all_screens: rendered.all_screens,
         screens,
-        frames_by_resolution,
+        frame_sets,
+        background_transitions: Vec::new(),
     }
 }
 
-/// Create GIF-decoder from file.
-fn create_decoder(path_to_gif: &str) -> gift::Decoder<File> {
-    gift::Decoder::new(File::open(path_to_gif).expect("Unable to read file"))
+/// Paints the background-color, -fill and -image of options onto all screens
+/// of the pixmap, covering any previous wallpaper. Frames are drawn over it,
+/// so it remains visible around them.
+pub fn paint_background(xcontext: &Box<XContext>, wallpapers: &Wallpapers, options: Arc<Options>) {
+    for screen in &wallpapers.all_screens {
+        unsafe {
+            XSetForeground(
+                xcontext.display,
+                xcontext.gc,
+                xcontext.background_color.pixel,
+            );
+            XFillRectangle(
+                xcontext.display,
+                xcontext.pixmap,
+                xcontext.gc,
+                screen.x_org,
+                screen.y_org,
+                screen.width,
+                screen.height,
+            );
+        }
+    }
+
+    if let Some(fill) = &options.background_fill {
+        paint_background_fill(xcontext, &wallpapers.all_screens, fill, &options);
+    }
+
+    if let Some(path) = &options.background_image {
+        match BackgroundImage::open(path) {
+            Ok(image) => paint_background_image(xcontext, &wallpapers.all_screens, &image, options),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
 }
 
-/// Parse GIF to gather the disposal-method for each frame.
-fn gather_disposal_methods(path_to_gif: &str) -> Vec<gift::block::DisposalMethod> {
-    let mut methods: Vec<gift::block::DisposalMethod> = Vec::new();
-    let frames = create_decoder(path_to_gif).into_frames();
-    for frame in frames {
-        if frame.is_ok() {
-            let f = frame.unwrap();
+/// Paints the image onto its area of each screen of the pixmap. Its area is
+/// the screen itself, or all screens if spanned.
+fn paint_background_image(
+    xcontext: &Box<XContext>,
+    screens: &[screens::Screen],
+    image: &BackgroundImage,
+    options: Arc<Options>,
+) {
+    let format = pixel_format(xcontext);
+
+    let areas: Vec<screens::Screen> = if options.span {
+        bounding_box(screens.iter()).into_iter().collect()
+    } else {
+        screens.to_vec()
+    };
+
+    for area in &areas {
+        logln!(options, "Paint background-image on {:?}", area);
+
+        let background = backdrop::place_background(
+            image,
+            &Resolution::new(area.width, area.height),
+            &format.background_rgba,
+            options.clone(),
+        );
 
-            if f.graphic_control_ext.is_some() {
-                methods.push(f.graphic_control_ext.unwrap().disposal_method());
+        let (mut ximage, mut xshminfo) = create_shared_image(xcontext, &background.resolution);
+        upload_raster(
+            &xshminfo,
+            &to_ximage_raster(&background.raster, &format.rgba_indices),
+        );
+
+        // Placements are relative to the area, which is also the raster
+        let visible = background
+            .placements
+            .iter()
+            .map(|placement| {
+                ImagePlacement::new(
+                    placement.dest_x,
+                    placement.dest_y,
+                    area.x_org + placement.dest_x,
+                    area.y_org + placement.dest_y,
+                    placement.width,
+                    placement.height,
+                )
+            })
+            .flat_map(|placement| {
+                screens
+                    .iter()
+                    .filter_map(move |screen| placement.clip_to_screen(screen))
+            });
+
+        for placement in visible {
+            unsafe {
+                x11::xshm::XShmPutImage(
+                    xcontext.display,
+                    xcontext.pixmap,
+                    xcontext.gc,
+                    ximage.as_mut(),
+                    placement.src_x,
+                    placement.src_y,
+                    placement.dest_x,
+                    placement.dest_y,
+                    placement.width,
+                    placement.height,
+                    False,
+                );
             }
+        }
 
-            continue;
+        unsafe {
+            // Server must be done with the image, before it is freed
+            XSync(xcontext.display, False);
+            x11::xshm::XShmDetach(xcontext.display, xshminfo.as_mut() as *mut _);
         }
 
-        methods.push(gift::block::DisposalMethod::NoAction);
+        destroy_xshm_sgmnt_inf(&mut xshminfo);
     }
+}
 
-    methods
+/// Area to place the background-image on: The screen itself, or the bounding
+/// box of all screens if spanned.
+fn background_area(
+    screen: &screens::Screen,
+    all_screens: &[screens::Screen],
+    options: &Options,
+) -> screens::Screen {
+    if options.span {
+        bounding_box(all_screens.iter()).unwrap_or_else(|| screen.clone())
+    } else {
+        screen.clone()
+    }
 }
 
-/// Render GIF-frames as bitmaps for a specific screen.
-fn render_frames(
+/// Paints the fill onto each screen of the pixmap.
+fn paint_background_fill(
     xcontext: &Box<XContext>,
-    wallpaper_on_screen: &WallpaperOnScreen,
-    steps: &mut gift::decode::Steps<File>,
-    methods: &Vec<gift::block::DisposalMethod>,
-    options: Arc<Options>,
-    running: Arc<AtomicBool>,
-) -> Vec<Frame> {
-    let mut rendered_frames: Vec<Frame> = Vec::new();
-    let mut frame_index = 0;
+    screens: &[screens::Screen],
+    fill: &BackgroundFill,
+    options: &Options,
+) {
+    let (first, second) = fill.colors();
+
+    let colors = match (
+        xcontext.rgba_of_color(first),
+        xcontext.rgba_of_color(second),
+    ) {
+        (Some(first), Some(second)) => [first, second],
+        _ => {
+            eprintln!(
+                "Unable to parse {} or {} as X11-color. Ignore fill.",
+                first, second
+            );
+            return;
+        }
+    };
 
-    let xscreen = unsafe { XDefaultScreenOfDisplay(xcontext.display) };
-    let xvisual = unsafe { XDefaultVisualOfScreen(xscreen) };
+    let format = pixel_format(xcontext);
+
+    for screen in screens {
+        logln!(options, "Paint {:?} on {:?}", fill, screen);
+
+        let resolution = Resolution::new(screen.width, screen.height);
+        let raster = fill::render(fill, &colors, &resolution);
 
-    // Convert rasters to frames
-    for step_option in steps.by_ref() {
-        if !running.load(Ordering::SeqCst) {
-            break;
+        let (mut ximage, mut xshminfo) = create_shared_image(xcontext, &resolution);
+        upload_raster(&xshminfo, &to_ximage_raster(&raster, &format.rgba_indices));
+
+        unsafe {
+            x11::xshm::XShmPutImage(
+                xcontext.display,
+                xcontext.pixmap,
+                xcontext.gc,
+                ximage.as_mut(),
+                0,
+                0,
+                screen.x_org,
+                screen.y_org,
+                screen.width,
+                screen.height,
+                False,
+            );
+
+            // Server must be done with the image, before it is freed
+            XSync(xcontext.display, False);
+            x11::xshm::XShmDetach(xcontext.display, xshminfo.as_mut() as *mut _);
         }
 
-        let step = step_option.expect("Unexpected end of steps");
-        let raster = step.raster();
+        destroy_xshm_sgmnt_inf(&mut xshminfo);
+    }
+}
 
-        let image_resolution = Resolution {
-            width: raster.width(),
-            height: raster.height(),
-        };
+/// Path of the file to show on the screen at `index`. The first file mapped
+/// to the screen by options, otherwise the default one.
+fn wallpaper_path<'a>(index: usize, screen: &screens::Screen, options: &'a Options) -> &'a str {
+    options
+        .screen_wallpapers
+        .iter()
+        .find(|wallpaper| wallpaper.selector.matches(index, screen))
+        .map_or(options.path_to_gif.as_str(), |wallpaper| {
+            wallpaper.path.as_str()
+        })
+}
 
-        let target_resolution = wallpaper_on_screen.resolution.clone();
+/// Renders the frames of a single file for all given keys. Uses cached frames
+/// and renders frames ahead in the background, if the memory-budget is
+/// exceeded.
+fn render_frame_sets(
+    path: &str,
+    source: Box<dyn FrameSource>,
+    keys: Vec<FrameSetKey>,
+    background: Option<&BackgroundImage>,
+    format: &PixelFormat,
+    memory_budget: Option<u64>,
+    options: Arc<Options>,
+    running: Arc<AtomicBool>,
+) -> Result<HashMap<FrameSetKey, RenderedFrames>, String> {
+    let cache = open_cache(path, format, options.clone());
+
+    if let Some(cache) = &cache {
+        if let Some(frames_by_key) =
+            load_cached_frames(cache, &keys, memory_budget, options.clone())
+        {
+            return Ok(frames_by_key);
+        }
+    }
 
-        logln!(
-            options,
-            "Convert step {} (delay: {:?}, method: {:?}, width: {}, height: {}) to XImage (width: {}, height: {})",
-            frame_index,
-            step.delay_time_cs(),
-            methods[frame_index],
-            image_resolution.width,
-            image_resolution.height,
-            target_resolution.width,
-            target_resolution.height
+    // Decode and compose all frames once, for all resolutions
+    let animation = match Animation::from_source(source) {
+        Ok(mut animation) => {
+            animation.override_delays(&options.frame_delays);
+            Arc::new(animation)
+        }
+        Err(e) => return Err(format!("File {} is not a valid image: {}", path, e)),
+    };
+
+    let composed_size = stream::composed_size(animation.frames.len(), &animation.resolution);
+
+    if memory_budget.map_or(false, |budget| composed_size > budget * 1024 * 1024) {
+        eprintln!(
+            "Decoded frames of {} alone take {} MiB, more than the memory-budget",
+            path,
+            composed_size / (1024 * 1024)
         );
+    }
 
-        // Build target raster
-        // TODO Better naming of vectors
-
-        // Create shared memory segment and image structure
-        let image_byte_size = (target_resolution.width * target_resolution.height * 4) as usize;
-
-        let mut xshminfo = create_xshm_sgmnt_inf(image_byte_size).unwrap();
-        let ximage = create_xshm_image(
-            xcontext.display,
-            xvisual,
-            &mut xshminfo,
-            target_resolution.width as u32,
-            target_resolution.height as u32,
-            24,
-        )
-        .unwrap();
-
-        let is_rgb = unsafe { (*ximage).byte_order == x11::xlib::MSBFirst };
-        let rgba_indices = if is_rgb {
-            [0, 1, 2, 3] // RGBA
-        } else {
-            [2, 1, 0, 3] // BGRA
-        };
+    let resolutions: Vec<Resolution> = keys.iter().map(|key| key.resolution.clone()).collect();
 
-        let color = xcontext.background_color;
-        let background_rgba = [
-            (color.red / 256) as u8,
-            (color.green / 256) as u8,
-            (color.blue / 256) as u8,
-            255 as u8,
-        ];
-
-        // Get previous frame as raster or plain color pane, if non-existing
-        let prev_raster: Rc<Vec<u8>> = {
-            if rendered_frames.len() > 0 {
-                rendered_frames.last().unwrap().raster.clone()
-            } else {
-                let capacity: usize = raster.width() as usize
-                    * raster.height() as usize
-                    * std::mem::size_of::<Rgba8>();
+    let stream_capacity = stream::capacity_for_budget(
+        animation.frames.len(),
+        composed_size,
+        &resolutions,
+        memory_budget,
+    );
 
-                // TODO Too big, needs only the size of original frame
-                let mut solid_color: Vec<u8> = Vec::with_capacity(capacity);
-                let mut solid_color_index: usize = 0;
+    let mut frames_by_key: HashMap<FrameSetKey, RenderedFrames> = HashMap::new();
 
-                while solid_color_index < capacity {
-                    solid_color.push(background_rgba[rgba_indices[0]]);
-                    solid_color.push(background_rgba[rgba_indices[1]]);
-                    solid_color.push(background_rgba[rgba_indices[2]]);
-                    solid_color.push(background_rgba[rgba_indices[3]]);
+    match stream_capacity {
+        Some(capacity) => {
+            for key in keys {
+                let backdrop = render_backdrop(&key, background, format, options.clone());
 
-                    solid_color_index += 4;
-                }
+                let frames = StreamedFrames::start(
+                    animation.clone(),
+                    &key.resolution,
+                    capacity,
+                    backdrop,
+                    *format,
+                    options.clone(),
+                    running.clone(),
+                );
 
-                Rc::new(solid_color)
+                frames_by_key.insert(key, RenderedFrames::Streamed(frames));
+            }
+        }
+        None => {
+            let mut uncached: Vec<FrameSetKey> = Vec::new();
+
+            for key in keys {
+                match cache.as_ref().and_then(|c| c.load(&key)) {
+                    Some(frames) => {
+                        logln!(options, "Use cached frames for {:?}", key);
+                        frames_by_key.insert(key, RenderedFrames::Prerendered(frames));
+                    }
+                    None => uncached.push(key),
+                }
             }
-        };
 
-        let u8_slice = raster.as_u8_slice();
-        let mut i = 0;
-
-        let mut data: Vec<u8> = Vec::with_capacity(image_byte_size);
-        while i < u8_slice.len() {
-            let alpha = u8_slice[i + 3];
-
-            if alpha == 255 {
-                data.push(u8_slice[i + rgba_indices[0]]);
-                data.push(u8_slice[i + rgba_indices[1]]);
-                data.push(u8_slice[i + rgba_indices[2]]);
-                data.push(u8_slice[i + rgba_indices[3]]);
-            } else if methods[frame_index] == gift::block::DisposalMethod::Keep {
-                data.push(prev_raster[i + 0]);
-                data.push(prev_raster[i + 1]);
-                data.push(prev_raster[i + 2]);
-                data.push(prev_raster[i + 3]);
-            } else {
-                data.push(background_rgba[rgba_indices[0]]);
-                data.push(background_rgba[rgba_indices[1]]);
-                data.push(background_rgba[rgba_indices[2]]);
-                data.push(alpha);
+            let rendered = render_frames(
+                &uncached,
+                &animation,
+                background,
+                format,
+                options.clone(),
+                running.clone(),
+            );
+
+            for (key, frames) in uncached.into_iter().zip(rendered) {
+                let is_complete = frames.len() == animation.frames.len();
+
+                if let (Some(cache), true) = (&cache, is_complete) {
+                    if let Err(e) = cache.store(&key, &frames) {
+                        eprintln!("{}", e);
+                    }
+                }
+
+                frames_by_key.insert(key, RenderedFrames::Prerendered(frames));
             }
+        }
+    }
+
+    Ok(frames_by_key)
+}
 
-            i += 4;
+/// Opens the cache of rendered frames of a file, unless disabled by options.
+fn open_cache(path: &str, format: &PixelFormat, options: Arc<Options>) -> Option<Cache> {
+    if options.no_cache {
+        return None;
+    }
+
+    match Cache::open(path, &options, format) {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            logln!(options, "Cache is not available: {}", e);
+            None
         }
+    }
+}
 
-        let frame_ptr = Rc::new(data);
-        let resized_frame = resize_raster(
-            frame_ptr.clone(),
-            &image_resolution,
-            &target_resolution,
-            options.clone(),
-        );
+/// Loads frames of all keys from cache, if all are cached and fit into the
+/// memory-budget. Otherwise nothing is loaded.
+fn load_cached_frames(
+    cache: &Cache,
+    keys: &[FrameSetKey],
+    memory_budget: Option<u64>,
+    options: Arc<Options>,
+) -> Option<HashMap<FrameSetKey, RenderedFrames>> {
+    let frame_count = cache.frame_count(keys.first()?)?;
+
+    for key in keys {
+        if cache.frame_count(key)? != frame_count {
+            return None;
+        }
+    }
+
+    let resolutions: Vec<Resolution> = keys.iter().map(|key| key.resolution.clone()).collect();
+
+    if stream::capacity_for_budget(frame_count, 0, &resolutions, memory_budget).is_some() {
+        return None;
+    }
+
+    let mut frames_by_key: HashMap<FrameSetKey, RenderedFrames> = HashMap::new();
+
+    for key in keys {
+        let frames = cache.load(key)?;
+
+        logln!(options, "Use cached frames for {:?}", key);
+        frames_by_key.insert(key.clone(), RenderedFrames::Prerendered(frames));
+    }
+
+    Some(frames_by_key)
+}
+
+/// Render all composed GIF-frames as bitmaps for the given keys. Frames are
+/// converted and scaled in parallel. Returns frames in order of keys.
+fn render_frames(
+    keys: &[FrameSetKey],
+    animation: &Animation,
+    background: Option<&BackgroundImage>,
+    format: &PixelFormat,
+    options: Arc<Options>,
+    running: Arc<AtomicBool>,
+) -> Vec<Vec<RenderedFrame>> {
+    let image_resolution = &animation.resolution;
+    let visible_resolution =
+        cropped_resolution(image_resolution, &options).unwrap_or_else(|| image_resolution.clone());
+    let frame_count = animation.frames.len();
+    let total = frame_count * keys.len();
+
+    let resolutions: Vec<&Resolution> = keys.iter().map(|key| &key.resolution).collect();
 
+    for target_resolution in &resolutions {
         logln!(
             options,
-            "Resized {}={}",
-            resized_frame.len(),
-            image_byte_size
+            "Render {} frames from {}x{} to {}x{}, using filter {}",
+            frame_count,
+            visible_resolution.width,
+            visible_resolution.height,
+            target_resolution.width,
+            target_resolution.height,
+            resampling::select_filter(&options, &visible_resolution, target_resolution).1
         );
+    }
 
-        // Copy raw data into shared memory segment of XImage
-        unsafe {
-            libc::memcpy(
-                xshminfo.shmaddr as *mut c_void,
-                resized_frame.as_ptr().clone() as *mut _,
-                image_byte_size,
-            );
-            (*ximage).data = xshminfo.shmaddr;
-            x11::xshm::XShmAttach(xcontext.display, xshminfo.as_mut() as *mut _);
-        };
+    // Render backdrops once per key, to paint all frames over
+    let backdrops: Vec<Vec<u8>> = keys
+        .iter()
+        .map(|key| render_backdrop(key, background, format, options.clone()))
+        .collect();
+
+    let mut frames: Vec<Vec<Option<RenderedFrame>>> = resolutions
+        .iter()
+        .map(|_| (0..frame_count).map(|_| None).collect())
+        .collect();
+    let mut rendered = 0;
+
+    pool::for_each_parallel(
+        total,
+        &running,
+        |job| {
+            render_raster(
+                &animation.frames[job % frame_count],
+                image_resolution,
+                resolutions[job / frame_count],
+                &backdrops[job / frame_count],
+                format,
+                options.clone(),
+            )
+        },
+        |job, raster| {
+            let (key_index, frame_index) = (job / frame_count, job % frame_count);
+
+            frames[key_index][frame_index] = Some(RenderedFrame {
+                delay: get_frame_duration(animation.frames[frame_index].delay_cs, options.clone()),
+                raster,
+            });
+
+            rendered += 1;
+
+            log!(options, "\rRendered {}/{} frames", rendered, total);
+            let _ = std::io::stdout().flush();
+        },
+    );
 
-        rendered_frames.push(Frame {
-            delay: get_step_duration(&step, options.clone()),
-            raster: frame_ptr,
-            ximage: unsafe { Box::new(*ximage) },
-            xshminfo,
-        });
+    log!(options, "\n");
 
-        frame_index = frame_index + 1;
-    }
+    frames
+        .into_iter()
+        .map(|frames| frames.into_iter().flatten().collect())
+        .collect()
+}
 
-    rendered_frames
+/// Renders the backdrop to paint the frames of the key over.
+fn render_backdrop(
+    key: &FrameSetKey,
+    background: Option<&BackgroundImage>,
+    format: &PixelFormat,
+    options: Arc<Options>,
+) -> Vec<u8> {
+    backdrop::render_backdrop(
+        background,
+        key.backdrop.as_ref(),
+        &scaled_resolution(&key.resolution, &options),
+        &format.background_rgba,
+        options.clone(),
+    )
 }
 
-/// Get delay from step. Normalize or use default if not below zere or not
-/// given.
-fn get_step_duration(step: &gift::Step, options: Arc<Options>) -> time::Duration {
-    let mut delay = step.delay_time_cs().unwrap_or(options.default_delay);
+/// Creates a shared memory segment and an XImage using it, in given
+/// resolution.
+fn create_shared_image(
+    xcontext: &Box<XContext>,
+    resolution: &Resolution,
+) -> (Box<XImage>, Box<x11::xshm::XShmSegmentInfo>) {
+    let xscreen = unsafe { XDefaultScreenOfDisplay(xcontext.display) };
+    let xvisual = unsafe { XDefaultVisualOfScreen(xscreen) };
 
-    if delay <= 0 {
-        delay = options.default_delay;
+    let image_byte_size = (resolution.width * resolution.height * 4) as usize;
+
+    let mut xshminfo = create_xshm_sgmnt_inf(image_byte_size).unwrap();
+    let ximage = create_xshm_image(
+        xcontext.display,
+        xvisual,
+        &mut xshminfo,
+        resolution.width,
+        resolution.height,
+        24,
+    )
+    .unwrap();
+
+    unsafe {
+        (*ximage).data = xshminfo.shmaddr;
+        x11::xshm::XShmAttach(xcontext.display, xshminfo.as_mut() as *mut _);
+
+        (Box::new(*ximage), xshminfo)
+    }
+}
+
+/// Copy raw data into shared memory segment of an XImage.
+fn upload_raster(xshminfo: &x11::xshm::XShmSegmentInfo, raster: &[u8]) {
+    unsafe {
+        libc::memcpy(
+            xshminfo.shmaddr as *mut c_void,
+            raster.as_ptr() as *const c_void,
+            raster.len(),
+        );
     }
+}
 
-    time::Duration::from_millis((delay * 10) as u64)
+/// Determine byte-order of XImages and background-color for pixels.
+fn pixel_format(xcontext: &Box<XContext>) -> PixelFormat {
+    let is_rgb = unsafe { XImageByteOrder(xcontext.display) } == x11::xlib::MSBFirst;
+    let rgba_indices = if is_rgb {
+        [0, 1, 2, 3] // RGBA
+    } else {
+        [2, 1, 0, 3] // BGRA
+    };
+
+    let color = xcontext.background_color;
+    let background_rgba = [
+        (color.red / 256) as u8,
+        (color.green / 256) as u8,
+        (color.blue / 256) as u8,
+        255 as u8,
+    ];
+
+    PixelFormat {
+        rgba_indices,
+        background_rgba,
+    }
 }
 
-/// Resize given RGBA-raster to target-resolution.
-fn resize_raster(
-    raster: Rc<Vec<u8>>,
+/// Render a composed frame as raster of an XImage in target-resolution. Its
+/// transparent pixels show the backdrop, rendered for `scaled_resolution`.
+fn render_raster(
+    composed_frame: &ComposedFrame,
     image_resolution: &Resolution,
     target_resolution: &Resolution,
+    backdrop: &[u8],
+    format: &PixelFormat,
     options: Arc<Options>,
-) -> Rc<Vec<u8>> {
-    let src_w = image_resolution.width as usize;
-    let src_h = image_resolution.height as usize;
-    let dst_w = target_resolution.width as usize;
-    let dst_h = target_resolution.height as usize;
+) -> Vec<u8> {
+    // Cut out the crop-region first, to convert and scale shown pixels only
+    let crop = options
+        .crop
+        .and_then(|crop| crop.clip_to_image(image_resolution));
+    let cropped = crop.map(|crop| raster::crop(&composed_frame.raster, image_resolution, &crop));
+    let image_resolution = &crop.map_or(image_resolution.clone(), |crop| crop.resolution());
+
+    let pixels = cropped.as_deref().unwrap_or(&composed_frame.raster);
+
+    let scaled_resolution = scaled_resolution(target_resolution, &options);
+
+    // Scale with premultiplied alpha and paint over the backdrop afterwards,
+    // so transparent pixels don't bleed into their neighbors
+    let rgba = if options.linear_light {
+        let linear = raster::premultiply_linear(pixels);
+        let scaled = resampling::resize_linear_raster(
+            linear,
+            image_resolution,
+            &scaled_resolution,
+            options.clone(),
+        );
+
+        raster::composite_linear_over(&scaled, backdrop)
+    } else {
+        let premultiplied = raster::premultiply(pixels);
+        let scaled = resampling::resize_raster(
+            premultiplied,
+            image_resolution,
+            &scaled_resolution,
+            options.clone(),
+        );
 
-    let must_resize = src_w != dst_w || src_h != dst_h;
+        raster::composite_over(&scaled, backdrop)
+    };
 
-    if !must_resize {
-        return raster.clone();
+    if options.scaling == Scaling::MIRROR_TILE {
+        let tile = raster::mirror_tile(&rgba, &scaled_resolution);
+        return to_ximage_raster(&tile, &format.rgba_indices);
     }
 
-    let (resize_type, type_name) = match options.scaling_filter {
-        ScalingFilter::PIXEL => (resize::Type::Point, "Point"),
-        ScalingFilter::AUTO => {
-            if (src_w * src_h) > (dst_w * dst_h) {
-                (resize::Type::Lanczos3, "Lanczos3")
-            } else {
-                (resize::Type::Mitchell, "Mitchell")
-            }
-        }
-    };
+    to_ximage_raster(&rgba, &format.rgba_indices)
+}
 
-    logln!(
-        options,
-        "Resize raster from {}x{} to {}x{}, using filter {}",
-        src_w,
-        src_h,
-        dst_w,
-        dst_h,
-        type_name
-    );
+/// Resolution to scale frames to. A single tile for MIRROR_TILE, which is
+/// mirrored into the others.
+fn scaled_resolution(target_resolution: &Resolution, options: &Options) -> Resolution {
+    if options.scaling == Scaling::MIRROR_TILE {
+        Resolution::new(target_resolution.width / 2, target_resolution.height / 2)
+    } else {
+        target_resolution.clone()
+    }
+}
 
-    let sample_size = dst_w * dst_h * 4;
+/// Resolution of the image to scale, after cutting out the crop-region of
+/// options. `None`, if the crop-region lies outside of the image.
+fn cropped_resolution(image_resolution: &Resolution, options: &Options) -> Option<Resolution> {
+    match options.crop {
+        Some(crop) => crop
+            .clip_to_image(image_resolution)
+            .map(|crop| crop.resolution()),
+        None => Some(image_resolution.clone()),
+    }
+}
+
+/// Convert opaque RGBA-raster to byte-order of XImage.
+fn to_ximage_raster(raster: &[u8], rgba_indices: &[usize; 4]) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::with_capacity(raster.len());
+
+    for pixel in raster.chunks_exact(4) {
+        for &index in rgba_indices {
+            data.push(pixel[index]);
+        }
+    }
 
-    let mut dst: Vec<u8> = Vec::with_capacity(sample_size);
-    dst.resize(sample_size, 0);
+    data
+}
 
-    let mut resizer = resize::new(src_w, src_h, dst_w, dst_h, resize::Pixel::RGBA, resize_type);
+/// Get delay of frame. Normalize or use default if not below zere or not
+/// given.
+fn get_frame_duration(delay_cs: Option<u16>, options: Arc<Options>) -> time::Duration {
+    let mut delay = delay_cs.unwrap_or(options.default_delay);
 
-    resizer.resize(&raster, &mut dst);
+    if delay <= 0 {
+        delay = options.default_delay;
+    }
 
-    Rc::new(dst)
+    time::Duration::from_millis(u64::from(delay) * 10)
 }
 
 /// Clear previous backgrounds on root.
@@ -409,11 +1231,22 @@ pub fn clear_background(xcontext: &Box<XContext>, options: Arc<Options>) {
     }
 }
 
-/// Loops the pre-renders wallpapers on each screen. Will only stop on
-/// interrupt-signal.
+/// Loops the pre-renders wallpapers on each screen, parking until the next
+/// frame of any screen is due. Will only stop on interrupt-signal. If all
+/// wallpapers are single frames or paused, the calling thread parks until
+/// woken up. So the interrupt-handler and the sender of `commands` must
+/// unpark it.
+///
+/// Files to swap to, changed files and the next file of the playlist are
+/// rendered in the background. Once done and due, they replace the wallpapers and the
+/// previous frames are freed. With a transition, the previous content of each
+/// screen is blended into the new frames, stepping between frames if needed.
 pub fn do_animation(
     xcontext: &Box<XContext>,
     wallpapers: &mut Wallpapers,
+    playlist: &mut Playlist,
+    commands: &Receiver<Request>,
+    watcher: &FileWatcher,
     options: Arc<Options>,
     running: Arc<AtomicBool>,
 ) {
@@ -427,64 +1260,439 @@ pub fn do_animation(
     let atom_root = get_root_pixmap_atom(display);
     let atom_eroot = get_eroot_pixmap_atom(display);
 
-    let mut i: usize = 0;
-    let mut delay: std::time::Duration = std::time::Duration::new(0, 0);
+    let initial_options = options.clone();
+    let mut options = options;
+    let mut playback = Playback::new();
+    let mut shown_at = Instant::now();
+    let handler = thread::current();
+
+    for path in source_paths(&options)
+        .into_iter()
+        .chain(options.playlist_file.as_deref())
+    {
+        watcher.watch(path);
+    }
 
-    while running.load(Ordering::SeqCst) {
-        if !running.load(Ordering::SeqCst) {
-            break;
-        }
+    thread::scope(|scope| {
+        let render_in_background = |options: Arc<Options>, is_swap: bool| {
+            for path in source_paths(&options) {
+                watcher.watch(path);
+            }
+
+            let render_options = options.clone();
+            let running = running.clone();
+            let handler = handler.clone();
+
+            // Only rasters are rendered in the background, the X-connection
+            // is used by this thread alone
+            let xscreens = Screens::query_x_screens();
+            let format = pixel_format(xcontext);
+
+            let rendering = scope.spawn(move || {
+                let rendered = render_rasters(xscreens, format, render_options, running);
+
+                handler.unpark();
+                rendered
+            });
+
+            Upcoming {
+                options,
+                rendering,
+                is_swap,
+                is_stale: false,
+            }
+        };
 
-        for screen in &wallpapers.screens {
-            let frames = wallpapers
-                .frames_by_resolution
-                .get_mut(&screen.resolution)
-                .unwrap();
+        let mut upcoming: Option<Upcoming> = None;
+        let mut is_reload_pending = false;
+
+        while running.load(Ordering::SeqCst) {
+            let mut is_stepping = false;
+
+            // Commands are handled between frames
+            for request in commands.try_iter() {
+                logln!(options, "Received command {:?}", request.command);
+
+                let reply = match request.command {
+                    Command::PAUSE => {
+                        playback.is_paused = true;
+                        "ok".to_string()
+                    }
+                    Command::RESUME => {
+                        playback.is_paused = false;
+                        "ok".to_string()
+                    }
+                    Command::NEXT_FRAME => {
+                        is_stepping = true;
+                        "ok".to_string()
+                    }
+                    Command::SET_SPEED(speed) => {
+                        playback.speed = speed;
+                        "ok".to_string()
+                    }
+                    Command::STATUS => describe_status(wallpapers, &playback),
+                    Command::SWAP(path) => match &upcoming {
+                        Some(pending) if pending.is_swap => {
+                            "error: Still rendering the previous wallpaper to swap".to_string()
+                        }
+                        _ => {
+                            if let Err(e) = decoder::open_frames(&path) {
+                                format!("error: {}", e)
+                            } else {
+                                let swapped = Arc::new(swapped_options(&options, &path));
+
+                                // The next file of the playlist is rendered
+                                // again after the swapped one
+                                match &mut upcoming {
+                                    // Discarded once rendered, then the
+                                    // swapped file is rendered instead
+                                    Some(pending) if !pending.rendering.is_finished() => {
+                                        pending.options = swapped;
+                                        pending.is_swap = true;
+                                        pending.is_stale = true;
+                                    }
+                                    _ => {
+                                        if let Some(pending) = upcoming.take() {
+                                            let _ = pending.rendering.join();
+                                        }
+
+                                        upcoming = Some(render_in_background(swapped, true));
+                                    }
+                                }
+
+                                "ok".to_string()
+                            }
+                        }
+                    },
+                };
+
+                // Client may have given up waiting already
+                let _ = request.reply.send(reply);
+            }
 
-            // The following assumptions only work, while there is a single GIF
-            // to render. Different GIFs per screen would require a rewrite.
+            // Changes are reported once files are written
+            let changed: Vec<PathBuf> = watcher.changes.try_iter().flatten().collect();
+
+            if !changed.is_empty() {
+                logln!(options, "Changed files {:?}", changed);
+
+                let is_affected = |options: &Options| {
+                    source_paths(options).iter().any(|path| {
+                        changed
+                            .iter()
+                            .any(|changed| watch::is_affected(path, changed))
+                    })
+                };
+
+                if let Some(playlist_file) = &initial_options.playlist_file {
+                    if changed
+                        .iter()
+                        .any(|changed| watch::is_affected(playlist_file, changed))
+                    {
+                        logln!(options, "Reload playlist {}", playlist_file);
+
+                        if let Err(e) = playlist.reload() {
+                            eprintln!("Unable to reload playlist: {}", e);
+                        }
+
+                        // The file rendered ahead may no longer be the next
+                        // one. It is discarded and rendered again once done.
+                        if let Some(pending) = &mut upcoming {
+                            if !pending.is_swap
+                                && pending.options.path_to_gif != playlist.upcoming()
+                            {
+                                pending.options =
+                                    Arc::new(listed_options(&initial_options, playlist.upcoming()));
+                                pending.is_stale = true;
+                            }
+                        }
+                    }
+                }
+
+                if let Some(pending) = &mut upcoming {
+                    pending.is_stale |= is_affected(&pending.options);
+                }
 
-            // Assumption: All framesets have same length
-            if frames.len() <= i {
-                i = 0;
+                is_reload_pending |= is_affected(&options);
             }
 
-            // Assumption: All frames with same index have same delay
-            delay = frames[i].delay;
+            // Render again from the changed files
+            if upcoming.as_ref().map_or(false, |pending| {
+                pending.is_stale && pending.rendering.is_finished()
+            }) {
+                let stale = upcoming.take().unwrap();
+                let _ = stale.rendering.join();
 
-            //logln!(options, "Put frame {} on screen {:?}", i, screen.placement);
+                upcoming = Some(render_in_background(stale.options, stale.is_swap));
+            }
 
-            unsafe {
-                x11::xshm::XShmPutImage(
-                    display,
-                    pixmap,
-                    gc,
-                    &mut *frames[i].ximage,
-                    screen.placement.src_x,
-                    screen.placement.src_y,
-                    screen.placement.dest_x,
-                    screen.placement.dest_y,
-                    screen.placement.width as c_uint,
-                    screen.placement.height as c_uint,
-                    False,
-                );
+            // Reload the shown files, replacing the next file of the playlist
+            // rendered ahead. Swapped files replace them anyway.
+            if is_reload_pending {
+                match &upcoming {
+                    Some(pending) if pending.is_swap => is_reload_pending = false,
+                    Some(pending) if !pending.rendering.is_finished() => {}
+                    _ => {
+                        if let Some(pending) = upcoming.take() {
+                            let _ = pending.rendering.join();
+                        }
+
+                        logln!(options, "Reload {}", options.path_to_gif);
+
+                        let reloaded = Arc::new(listed_options(&options, &options.path_to_gif));
+                        upcoming = Some(render_in_background(reloaded, true));
+
+                        is_reload_pending = false;
+                    }
+                }
             }
-        }
 
-        i = i + 1;
+            // Render the next file of the playlist ahead
+            if upcoming.is_none() && playlist.len() > 1 {
+                let next = Arc::new(listed_options(&initial_options, playlist.upcoming()));
 
-        if !update_root_pixmap_atoms(display, root, &pixmap, atom_root, atom_eroot) {
-            eprintln!("set_root_atoms failed!");
-        }
+                logln!(options, "Render next file {} ahead", next.path_to_gif);
 
-        unsafe {
-            XClearWindow(display, root);
-            XSetWindowBackgroundPixmap(display, root, pixmap);
-            XSync(display, False);
+                upcoming = Some(render_in_background(next, false));
+            }
+
+            // Switch over at once, when the other wallpapers are rendered and
+            // the current ones were shown long enough
+            let is_switching = upcoming.as_ref().map_or(false, |pending| {
+                !pending.is_stale
+                    && pending.rendering.is_finished()
+                    && (pending.is_swap
+                        || (!playback.is_paused
+                            && options.play_length.is_over(
+                                shown_at,
+                                wallpapers.least_loops(),
+                                Instant::now(),
+                            )))
+            });
+
+            if is_switching {
+                let Upcoming {
+                    options: next_options,
+                    rendering,
+                    is_swap,
+                    ..
+                } = upcoming.take().unwrap();
+
+                if !is_swap {
+                    playlist.advance();
+                }
+
+                let is_shown = match rendering.join() {
+                    Ok(Ok(rendered)) => {
+                        logln!(options, "Swap wallpaper for {}", next_options.path_to_gif);
+
+                        let mut next = upload_wallpapers(xcontext, rendered);
+
+                        let now = Instant::now();
+
+                        // Capture the content of all screens, before painting
+                        // over it. Also those without a visible wallpaper
+                        // blend into the background.
+                        let transitions: Vec<(screens::Screen, TransitionOnScreen)> = next
+                            .all_screens
+                            .iter()
+                            .filter_map(|screen| {
+                                TransitionOnScreen::start(xcontext, screen, &next_options, now)
+                                    .map(|transition| (screen.clone(), transition))
+                            })
+                            .collect();
+
+                        paint_background(xcontext, &next, next_options.clone());
+
+                        for (screen, mut transition) in transitions {
+                            transition.capture_backdrop(xcontext, &screen);
+
+                            match next.screens.iter_mut().find(|shown| shown.screen == screen) {
+                                Some(shown) => shown.transition = Some(transition),
+                                None => next.background_transitions.push((screen, transition)),
+                            }
+                        }
+
+                        next.restart(now);
+
+                        let previous = std::mem::replace(wallpapers, next);
+                        clean_up(xcontext, previous, options.clone());
+
+                        options = next_options;
+
+                        // Show the first frames, also if paused
+                        is_stepping = true;
+                        true
+                    }
+                    Ok(Err(e)) => {
+                        eprintln!("Unable to swap wallpaper: {}", e);
+                        false
+                    }
+                    Err(_) => {
+                        eprintln!("Unable to swap wallpaper, rendering failed");
+                        false
+                    }
+                };
+
+                // A failed file of the playlist is skipped, the current one
+                // is shown for another play-length
+                if is_shown || !is_swap {
+                    shown_at = Instant::now();
+                }
+            }
+
+            let now = Instant::now();
+            let mut is_changed = false;
+
+            // Draw the due frames only, each screen follows its own timeline.
+            // Transitions are drawn in between.
+            for screen in wallpapers.screens.iter_mut() {
+                let is_due = !playback.is_paused && screen.timeline.is_due(now);
+                let is_drawing = is_due || is_stepping;
+
+                if !is_drawing && screen.transition.is_none() {
+                    continue;
+                }
+
+                let index = match &screen.transition {
+                    Some(transition) if !is_drawing => transition.frame_index,
+                    _ => screen.timeline.frame_index(),
+                };
+
+                // Blend the last frame of a loop into the first
+                if is_drawing
+                    && index == 0
+                    && screen.timeline.loops() > 0
+                    && options.transition_loops
+                    && screen.transition.is_none()
+                {
+                    screen.transition =
+                        TransitionOnScreen::start(xcontext, &screen.screen, &options, now);
+                }
+
+                let frames = wallpapers.frame_sets.get_mut(&screen.frame_set).unwrap();
+                let (ximage, delay) = frames.frame(index);
+
+                let is_done = match &mut screen.transition {
+                    Some(transition) => {
+                        if is_drawing {
+                            transition.paint_frame(&screen.screen, &screen.placements, ximage);
+                        }
+
+                        transition.frame_index = index;
+                        transition.draw(xcontext, &screen.screen, now)
+                    }
+                    None => {
+                        // All tiles are drawn from the same image in shared memory
+                        for placement in &screen.placements {
+                            unsafe {
+                                x11::xshm::XShmPutImage(
+                                    display,
+                                    pixmap,
+                                    gc,
+                                    ximage,
+                                    placement.src_x,
+                                    placement.src_y,
+                                    placement.dest_x,
+                                    placement.dest_y,
+                                    placement.width as c_uint,
+                                    placement.height as c_uint,
+                                    False,
+                                );
+                            }
+                        }
+
+                        false
+                    }
+                };
+
+                if is_done {
+                    if let Some(mut transition) = screen.transition.take() {
+                        transition.free(display);
+                    }
+                }
+
+                if is_drawing {
+                    screen.timeline.advance(playback.scale(delay), now);
+                }
+
+                is_changed = true;
+            }
+
+            // Screens without a visible wallpaper show the background only
+            wallpapers
+                .background_transitions
+                .retain_mut(|(screen, transition)| {
+                    is_changed = true;
+
+                    let is_done = transition.draw(xcontext, screen, now);
+
+                    if is_done {
+                        transition.free(display);
+                    }
+
+                    !is_done
+                });
+
+            if is_changed {
+                if !update_root_pixmap_atoms(display, root, &pixmap, atom_root, atom_eroot) {
+                    eprintln!("set_root_atoms failed!");
+                }
+
+                unsafe {
+                    XClearWindow(display, root);
+                    XSetWindowBackgroundPixmap(display, root, pixmap);
+                    XSync(display, False);
+                }
+            }
+
+            let mut next_due =
+                scheduler::next_due(wallpapers.screens.iter().map(|screen| &screen.timeline));
+
+            if playlist.len() > 1 {
+                let deadline = options
+                    .play_length
+                    .deadline(shown_at, wallpapers.least_loops());
+
+                next_due = next_due.into_iter().chain(deadline).min();
+            }
+
+            let is_transitioning = !wallpapers.background_transitions.is_empty()
+                || wallpapers
+                    .screens
+                    .iter()
+                    .any(|screen| screen.transition.is_some());
+
+            // Next step of the transitions, unless a frame is due earlier
+            if is_transitioning {
+                let step = Instant::now() + TRANSITION_INTERVAL;
+
+                next_due = Some(match next_due {
+                    Some(due) if !playback.is_paused => due.min(step),
+                    _ => step,
+                });
+            }
+
+            // Woken up early by the interrupt-handler or a command. Wake-ups in
+            // between are spurious, the loop just checks again.
+            match next_due {
+                Some(due) if !playback.is_paused || is_transitioning => {
+                    thread::park_timeout(due.saturating_duration_since(Instant::now()))
+                }
+                Some(_) => thread::park(),
+                None => {
+                    logln!(options, "Single frames only, idle until interrupted");
+                    thread::park();
+                }
+            }
         }
 
-        thread::sleep(delay);
-    }
+        // Wallpapers rendered too late to be swapped are dropped, which
+        // also stops their streams
+        if let Some(pending) = upcoming {
+            let _ = pending.rendering.join();
+        }
+    });
 
     logln!(options, "Stop animation-loop");
 
@@ -492,19 +1700,130 @@ pub fn do_animation(
     delete_atom(&xcontext, atom_eroot);
 }
 
+/// Describes the playback and the next frame of each screen.
+fn describe_status(wallpapers: &Wallpapers, playback: &Playback) -> String {
+    let mut status = vec![
+        format!(
+            "state: {}",
+            if playback.is_paused {
+                "paused"
+            } else {
+                "playing"
+            }
+        ),
+        format!("speed: {}", playback.speed),
+    ];
+
+    for screen in &wallpapers.screens {
+        status.push(format!(
+            "screen {}: {}, next frame {}/{}",
+            screen.screen.screen_number,
+            screen.frame_set.path,
+            screen.timeline.frame_index() + 1,
+            wallpapers.frame_sets[&screen.frame_set].len()
+        ));
+    }
+
+    status.join("\n")
+}
+
+/// Options to show the file on all screens instead. The cache is not cleared
+/// again.
+fn swapped_options(options: &Options, path: &str) -> Options {
+    let mut swapped = options.clone();
+
+    swapped.path_to_gif = path.to_string();
+    swapped.screen_wallpapers.clear();
+    swapped.clear_cache = false;
+
+    swapped
+}
+
+/// Files and image-sequences the wallpapers of the options are rendered from.
+fn source_paths(options: &Options) -> Vec<&str> {
+    let mut paths = vec![options.path_to_gif.as_str()];
+
+    paths.extend(
+        options
+            .screen_wallpapers
+            .iter()
+            .map(|wallpaper| wallpaper.path.as_str()),
+    );
+    paths.extend(options.background_image.as_deref());
+
+    paths
+}
+
+/// Options to show the next file of the playlist, on all screens without a
+/// file of their own. The cache is not cleared again.
+fn listed_options(options: &Options, path: &str) -> Options {
+    let mut listed = options.clone();
+
+    listed.path_to_gif = path.to_string();
+    listed.clear_cache = false;
+
+    listed
+}
+
 /// Clears reference and (shared-)-memory.
-pub fn clean_up(xcontext: Box<XContext>, mut wallpapers: Wallpapers, options: Arc<Options>) {
+pub fn clean_up(xcontext: &Box<XContext>, mut wallpapers: Wallpapers, options: Arc<Options>) {
     logln!(options, "Free images in shared memory");
 
-    for frames in wallpapers.frames_by_resolution.values_mut() {
-        for i in 0..(frames.len()) {
-            // Don't need to call XDestroy image - heap is freed by rust-guarantees. :)
-            unsafe {
-                x11::xshm::XShmDetach(xcontext.display, frames[i].xshminfo.as_mut() as *mut _)
-            };
-            destroy_xshm_sgmnt_inf(&mut frames[i].xshminfo);
+    for frames in wallpapers.frame_sets.values_mut() {
+        frames.free(xcontext.display);
+    }
+
+    for screen in wallpapers.screens.iter_mut() {
+        if let Some(mut transition) = screen.transition.take() {
+            transition.free(xcontext.display);
         }
     }
+
+    for (_, transition) in wallpapers.background_transitions.iter_mut() {
+        transition.free(xcontext.display);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::get_frame_duration;
+    use crate::options::Options;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn when_frame_delay_exceeds_u16_in_millis_then_dont_overflow() {
+        let options = Arc::new(Options::_from_params(vec![
+            "xgifwallpaper",
+            "--frame-delays",
+            "7000",
+            "a.gif",
+        ]));
+
+        assert_eq!(
+            get_frame_duration(Some(options.frame_delays[0]), options.clone()),
+            Duration::from_secs(70)
+        );
+    }
+
+    #[test]
+    fn when_frame_delay_is_missing_or_zero_then_use_default() {
+        let options = Arc::new(Options::_from_params(vec![
+            "xgifwallpaper",
+            "-d",
+            "7",
+            "a.gif",
+        ]));
+
+        assert_eq!(
+            get_frame_duration(None, options.clone()),
+            Duration::from_millis(70)
+        );
+        assert_eq!(
+            get_frame_duration(Some(0), options.clone()),
+            Duration::from_millis(70)
+        );
+    }
 }
 
 #[cfg(all(test, feature = "x11-integration-tests"))]
@@ -552,9 +1871,10 @@ mod test {
         };
 
         // Act
-        let wallpapers = render_wallpapers(&xcontext, screens, options.clone(), running.clone());
+        let wallpapers =
+            render_wallpapers(&xcontext, screens, options.clone(), running.clone()).unwrap();
 
         assert_eq!(wallpapers.screens.len(), 2);
-        assert_eq!(wallpapers.frames_by_resolution.len(), 2);
+        assert_eq!(wallpapers.frame_sets.len(), 2);
     }
 }
@@ -1,14 +1,38 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread;
 
+use xgifwallpaper::control::{self, ControlServer};
 use xgifwallpaper::options::Options;
+use xgifwallpaper::playlist::Playlist;
 use xgifwallpaper::screens::Screens;
+use xgifwallpaper::watch::FileWatcher;
 use xgifwallpaper::xcontext::XContext;
 use xgifwallpaper::*;
 
 /// Application entry-point
 fn main() {
-    let options = Arc::new(Options::from_args());
+    // Control a running instance, instead of starting one
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("ctl") {
+        std::process::exit(control::ctl(&args[2..]));
+    }
+
+    let mut options = Options::from_args();
+
+    let mut playlist = match Playlist::open(&options) {
+        Ok(playlist) => playlist,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(EXIT_INVALID_FILE);
+        }
+    };
+
+    // Shuffled playlists may start with any file
+    options.path_to_gif = playlist.current().to_string();
+
+    let options = Arc::new(options);
     let running = Arc::new(AtomicBool::new(true));
 
     init_sigint_handler(options.clone(), running.clone());
@@ -21,26 +45,47 @@ fn main() {
         }
     };
 
-    let mut wallpapers = render_wallpapers(
+    let mut wallpapers = match render_wallpapers(
         &xcontext,
         Screens::query_x_screens(),
         options.clone(),
         running.clone(),
-    );
+    ) {
+        Ok(wallpapers) => wallpapers,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(EXIT_INVALID_FILE);
+        }
+    };
 
+    paint_background(&xcontext, &wallpapers, options.clone());
     clear_background(&xcontext, options.clone());
 
-    do_animation(&xcontext, &mut wallpapers, options.clone(), running.clone());
+    let control = ControlServer::start(options.clone(), thread::current());
+    let watcher = FileWatcher::start(options.clone(), thread::current());
+
+    do_animation(
+        &xcontext,
+        &mut wallpapers,
+        &mut playlist,
+        &control.commands,
+        &watcher,
+        options.clone(),
+        running.clone(),
+    );
 
-    clean_up(xcontext, wallpapers, options);
+    clean_up(&xcontext, wallpapers, options);
 }
 
-/// Register handler for interrupt-signal.
+/// Register handler for interrupt-signal. Wakes up the calling thread, if
+/// parked while showing a still image.
 fn init_sigint_handler<'a>(options: Arc<Options>, running: Arc<AtomicBool>) {
     let verbose = options.verbose;
+    let main_thread = thread::current();
 
     ctrlc::set_handler(move || {
         running.store(false, Ordering::SeqCst);
+        main_thread.unpark();
 
         if verbose {
             println!("SIGINT received");
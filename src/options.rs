@@ -1,39 +1,159 @@
 //! Defines options of `xgifwallpaper` and parses these from command line-
 //! arguments.
 
+use std::time::Duration;
+
 use clap::{value_t, App, Arg, ArgMatches};
 
+use super::fill::BackgroundFill;
+use super::playlist::PlayLength;
+use super::position::Alignment;
+use super::position::Crop;
+use super::position::Offset;
+use super::position::Resolution;
 use super::position::Scaling;
 use super::position::ScalingFilter;
+use super::position::{TargetSize, MAX_TARGET_SIZE};
+use super::screens::ScreenSelector;
+use super::transition::Transition;
 use super::VERSION;
 
+const ARG_ALIGN: &str = "ALIGN";
+const ARG_BACKGROUND_FILL: &str = "BACKGROUND_FILL";
+const ARG_BACKGROUND_IMAGE: &str = "BACKGROUND_IMAGE";
+const ARG_CLEAR_CACHE: &str = "CLEAR_CACHE";
 const ARG_COLOR: &str = "COLOR";
+const ARG_CROP: &str = "CROP";
 const ARG_DELAY: &str = "DELAY";
+const ARG_DOWNSCALE_FILTER: &str = "DOWNSCALE_FILTER";
+const ARG_FRAME_DELAYS: &str = "FRAME_DELAYS";
+const ARG_LINEAR_LIGHT: &str = "LINEAR_LIGHT";
+const ARG_LOOPS: &str = "LOOPS";
+const ARG_MEMORY_BUDGET: &str = "MEMORY_BUDGET";
+const ARG_MINUTES: &str = "MINUTES";
+const ARG_NO_CACHE: &str = "NO_CACHE";
+const ARG_OFFSET: &str = "OFFSET";
 const ARG_PATH_TO_GIF: &str = "PATH_TO_GIF";
+const ARG_PLAYLIST: &str = "PLAYLIST";
 const ARG_SCALE: &str = "SCALE";
 const ARG_SCALE_FILTER: &str = "SCALE_FILTER";
+const ARG_SCREEN: &str = "SCREEN";
+const ARG_SHUFFLE: &str = "SHUFFLE";
+const ARG_SIZE: &str = "SIZE";
+const ARG_SPAN: &str = "SPAN";
+const ARG_TRANSITION: &str = "TRANSITION";
+const ARG_TRANSITION_DURATION: &str = "TRANSITION_DURATION";
+const ARG_TRANSITION_LOOPS: &str = "TRANSITION_LOOPS";
+const ARG_UPSCALE_FILTER: &str = "UPSCALE_FILTER";
 const ARG_VERBOSE: &str = "VERBOSE";
+const ARG_WATCH: &str = "WATCH";
 const ARG_WINDOW_ID: &str = "WINDOW_ID";
+const ARG_ZOOM: &str = "ZOOM";
+
+const SCALING_FILTERS: [&str; 8] = [
+    "AUTO",
+    "PIXEL",
+    "TRIANGLE",
+    "CATMULL_ROM",
+    "MITCHELL",
+    "LANCZOS3",
+    "SCALE2X",
+    "SCALE3X",
+];
+
+/// Filters to scale down with. Pixel-art upscalers never scale down.
+const DOWNSCALING_FILTERS: [&str; 6] = [
+    "AUTO",
+    "PIXEL",
+    "TRIANGLE",
+    "CATMULL_ROM",
+    "MITCHELL",
+    "LANCZOS3",
+];
 
 const DEFAULT_DELAY: u16 = 10;
 const DEFAULT_DELAY_STR: &str = "10";
 
+const DEFAULT_PLAY_MINUTES: u64 = 10;
+
+/// A year, longer than any file is ever shown.
+const MAX_PLAY_MINUTES: f64 = 365.0 * 24.0 * 60.0;
+
+const DEFAULT_TRANSITION_DURATION: u64 = 1000;
+const DEFAULT_TRANSITION_DURATION_STR: &str = "1000";
+
 /// Runtime options as given by the caller of this program.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Options {
+    /// Alignment of the GIF on the screen.
+    pub alignment: Alignment,
     /// X11-compilant color-name
     pub background_color: String,
+    /// Gradient or pattern to fill the background with, instead of
+    /// `background_color`.
+    pub background_fill: Option<BackgroundFill>,
+    /// Still image to show through transparent pixels of the GIF.
+    pub background_image: Option<String>,
+    /// Remove all cached frames on start.
+    pub clear_cache: bool,
+    /// Region to cut out of every frame, before scaling.
+    pub crop: Option<Crop>,
     pub default_delay: u16,
+    /// Filter to scale down with, instead of `scaling_filter`.
+    pub downscaling_filter: Option<ScalingFilter>,
+    /// Delays in centiseconds of the first frames, overriding the delays of
+    /// the file.
+    pub frame_delays: Vec<u16>,
+    /// Scale in linear light, instead of sRGB-values.
+    pub linear_light: bool,
+    /// Maximum of memory in MiB to use for rendered frames. If all frames do
+    /// not fit, they are rendered ahead in the background while animating.
+    pub memory_budget: Option<u64>,
+    /// Neither use nor write cached frames.
+    pub no_cache: bool,
+    /// Pixels to move the GIF from its aligned position.
+    pub offset: Offset,
+    /// First file to show. Empty, if only given by `playlist_file`.
     pub path_to_gif: String,
+    /// How long each file of a playlist is shown.
+    pub play_length: PlayLength,
+    /// Files to show in turn after `path_to_gif`.
+    pub playlist: Vec<String>,
+    /// File listing more files to show in turn, one per line.
+    pub playlist_file: Option<String>,
     /// Scaling-method to use
     pub scaling: Scaling,
     pub scaling_filter: ScalingFilter,
+    /// Files to show on specific screens, instead of `path_to_gif`.
+    pub screen_wallpapers: Vec<ScreenWallpaper>,
+    /// Play the files of a playlist in random order.
+    pub shuffle: bool,
+    /// Treat all screens showing the same file as a single canvas.
+    pub span: bool,
+    /// Explicit size of the GIF, instead of scaling it to the screen.
+    pub target_size: Option<TargetSize>,
+    /// Blend wallpapers into each other, instead of a hard cut.
+    pub transition: Option<Transition>,
+    pub transition_duration: Duration,
+    /// Also blend the last frame of each loop into the first.
+    pub transition_loops: bool,
+    /// Filter to scale up with, instead of `scaling_filter`.
+    pub upscaling_filter: Option<ScalingFilter>,
     pub verbose: bool,
+    /// Reload shown files and the playlist-file, when changed.
+    pub watch: bool,
     /// Window-Id as decimal or hex-number (0x-prefix) or name of atom with Id
     /// to use.
     pub window_id: String,
 }
 
+/// File to show on a specific screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScreenWallpaper {
+    pub selector: ScreenSelector,
+    pub path: String,
+}
+
 impl Options {
     /// Parse options from command-line.
     ///
@@ -70,6 +190,25 @@ fn init_args<'a, 'b>() -> App<'a, 'b> {
         .version(VERSION)
         .author("Frank Großgasteiger <frank@grossgasteiger.de>")
         .about("Animates a GIF as wallpaper in your X-session")
+        .arg(
+            Arg::with_name(ARG_ALIGN)
+                .short("a")
+                .long("align")
+                .takes_value(true)
+                .possible_values(&[
+                    "TOP_LEFT",
+                    "TOP",
+                    "TOP_RIGHT",
+                    "LEFT",
+                    "CENTER",
+                    "RIGHT",
+                    "BOTTOM_LEFT",
+                    "BOTTOM",
+                    "BOTTOM_RIGHT",
+                ])
+                .default_value("CENTER")
+                .help("Align GIF-frames, relative to available screen."),
+        )
         .arg(
             Arg::with_name(ARG_COLOR)
                 .short("b")
@@ -79,6 +218,38 @@ fn init_args<'a, 'b>() -> App<'a, 'b> {
                 .default_value("#000000")
                 .help("X11 compilant color-name to paint background."),
         )
+        .arg(
+            Arg::with_name(ARG_BACKGROUND_FILL)
+                .long("background-fill")
+                .takes_value(true)
+                .value_name("FILL")
+                .help(
+                    "Fill background around GIF-frames with LINEAR:color,color[,angle], \
+                    RADIAL:color,color or CHECKERBOARD:color,color[,size].",
+                ),
+        )
+        .arg(
+            Arg::with_name(ARG_BACKGROUND_IMAGE)
+                .long("background-image")
+                .takes_value(true)
+                .value_name("file")
+                .help(
+                    "Image to show around and through transparent pixels of \
+                    GIF-frames, scaled like these.",
+                ),
+        )
+        .arg(
+            Arg::with_name(ARG_CLEAR_CACHE)
+                .long("clear-cache")
+                .help("Remove all cached frames on start."),
+        )
+        .arg(
+            Arg::with_name(ARG_CROP)
+                .long("crop")
+                .takes_value(true)
+                .value_name("x,y,w,h")
+                .help("Cut this region out of GIF-frames before scaling, like 10,10,300,200."),
+        )
         .arg(
             Arg::with_name(ARG_DELAY)
                 .short("d")
@@ -88,19 +259,100 @@ fn init_args<'a, 'b>() -> App<'a, 'b> {
                 .default_value(DEFAULT_DELAY_STR)
                 .help("Delay in centiseconds between frames, if unspecified in GIF."),
         )
+        .arg(
+            Arg::with_name(ARG_DOWNSCALE_FILTER)
+                .long("downscale-filter")
+                .takes_value(true)
+                .value_name("FILTER")
+                .possible_values(&DOWNSCALING_FILTERS)
+                .help("Filter to scale down with, instead of scale-filter."),
+        )
+        .arg(
+            Arg::with_name(ARG_FRAME_DELAYS)
+                .long("frame-delays")
+                .takes_value(true)
+                .value_name("delays")
+                .help(
+                    "Comma-separated delays in centiseconds of the first frames, \
+                    overriding the delays of the file.",
+                ),
+        )
+        .arg(
+            Arg::with_name(ARG_LINEAR_LIGHT)
+                .long("linear-light")
+                .help("Scale in linear light, to keep the brightness of fine details."),
+        )
+        .arg(
+            Arg::with_name(ARG_LOOPS)
+                .long("loops")
+                .takes_value(true)
+                .value_name("N")
+                .conflicts_with(ARG_MINUTES)
+                .help("Show each file of a playlist for N loops, stills for a minute."),
+        )
+        .arg(
+            Arg::with_name(ARG_MEMORY_BUDGET)
+                .long("memory-budget")
+                .takes_value(true)
+                .value_name("MiB")
+                .help(
+                    "Maximum of memory to use for rendered frames. If exceeded, \
+                    frames are rendered ahead in the background, while animating.",
+                ),
+        )
+        .arg(
+            Arg::with_name(ARG_MINUTES)
+                .long("minutes")
+                .takes_value(true)
+                .value_name("N")
+                .help("Show each file of a playlist for N minutes. Default is 10."),
+        )
+        .arg(
+            Arg::with_name(ARG_NO_CACHE)
+                .long("no-cache")
+                .help("Neither use nor write cached frames of previous starts."),
+        )
+        .arg(
+            Arg::with_name(ARG_OFFSET)
+                .long("offset")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .value_name("x,y")
+                .help("Move GIF-frames by pixels from their aligned position, like 10,-20."),
+        )
         .arg(Arg::with_name(ARG_VERBOSE).short("v").help("Verbose mode"))
         .arg(
             Arg::with_name(ARG_PATH_TO_GIF)
-                .help("Path to GIF-file")
-                .required(true)
+                .help(
+                    "Path to GIF-, PNG-, WebP-, JPEG- or BMP-file. Or to a \
+                    directory or quoted glob of image-files as frames. Give \
+                    more paths to show them in turn.",
+                )
+                .required_unless(ARG_PLAYLIST)
+                .multiple(true)
                 .index(1),
         )
+        .arg(
+            Arg::with_name(ARG_PLAYLIST)
+                .long("playlist")
+                .takes_value(true)
+                .value_name("file")
+                .help("File with paths of more files to show in turn, one per line."),
+        )
         .arg(
             Arg::with_name(ARG_SCALE)
                 .short("s")
                 .long("scale")
                 .takes_value(true)
-                .possible_values(&["NONE", "FILL", "MAX"])
+                .possible_values(&[
+                    "NONE",
+                    "FILL",
+                    "MAX",
+                    "STRETCH",
+                    "INTEGER",
+                    "TILE",
+                    "MIRROR_TILE",
+                ])
                 .default_value("NONE")
                 .help("Scale GIF-frames, relative to available screen."),
         )
@@ -108,9 +360,73 @@ fn init_args<'a, 'b>() -> App<'a, 'b> {
             Arg::with_name(ARG_SCALE_FILTER)
                 .long("scale-filter")
                 .takes_value(true)
-                .possible_values(&["AUTO", "PIXEL"])
+                .possible_values(&SCALING_FILTERS)
                 .default_value("AUTO")
-                .help("Filter to use in combination with scale-option. Experimental feature."),
+                .help("Filter to use in combination with scale-option."),
+        )
+        .arg(
+            Arg::with_name(ARG_SCREEN)
+                .long("screen")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("SCREEN=PATH")
+                .help(
+                    "Show another file on a screen, selected by its index or \
+                    its origin like +1920+0. Repeat for more screens.",
+                ),
+        )
+        .arg(
+            Arg::with_name(ARG_SHUFFLE)
+                .long("shuffle")
+                .help("Show the files of a playlist in random order."),
+        )
+        .arg(
+            Arg::with_name(ARG_SIZE)
+                .long("size")
+                .takes_value(true)
+                .value_name("WIDTHxHEIGHT")
+                .conflicts_with(ARG_ZOOM)
+                .help("Scale GIF-frames to this size, like 1280x720, instead of scale-option."),
+        )
+        .arg(
+            Arg::with_name(ARG_SPAN)
+                .long("span")
+                .help("Span the GIF across all screens, as one canvas."),
+        )
+        .arg(
+            Arg::with_name(ARG_TRANSITION)
+                .long("transition")
+                .takes_value(true)
+                .possible_values(&["CROSSFADE", "WIPE", "SLIDE"])
+                .help("Blend into the next file of a playlist or a swapped one."),
+        )
+        .arg(
+            Arg::with_name(ARG_TRANSITION_DURATION)
+                .long("transition-duration")
+                .takes_value(true)
+                .value_name("ms")
+                .default_value(DEFAULT_TRANSITION_DURATION_STR)
+                .help("Duration of a transition in milliseconds."),
+        )
+        .arg(
+            Arg::with_name(ARG_TRANSITION_LOOPS)
+                .long("transition-loops")
+                .requires(ARG_TRANSITION)
+                .help("Also blend the last frame of each loop into the first."),
+        )
+        .arg(
+            Arg::with_name(ARG_UPSCALE_FILTER)
+                .long("upscale-filter")
+                .takes_value(true)
+                .value_name("FILTER")
+                .possible_values(&SCALING_FILTERS)
+                .help("Filter to scale up with, instead of scale-filter."),
+        )
+        .arg(
+            Arg::with_name(ARG_WATCH)
+                .long("watch")
+                .help("Reload shown files and the playlist-file, once changed and written."),
         )
         .arg(
             Arg::with_name(ARG_WINDOW_ID)
@@ -123,6 +439,13 @@ fn init_args<'a, 'b>() -> App<'a, 'b> {
                 .short("w")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name(ARG_ZOOM)
+                .long("zoom")
+                .takes_value(true)
+                .value_name("factor")
+                .help("Scale GIF-frames by this factor, like 3 or 0.5, instead of scale-option."),
+        )
 }
 
 /// Parse arguments from command line.
@@ -136,37 +459,302 @@ fn parse_args<'a>(args: ArgMatches<'a>) -> Options {
         DEFAULT_DELAY
     });
 
+    let frame_delays = match args.value_of(ARG_FRAME_DELAYS) {
+        Some(delays) => parse_frame_delays(delays).unwrap_or_else(|_e| {
+            eprintln!("Use comma-separated centiseconds as frame-delays. Ignore frame-delays.");
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+
+    let memory_budget = if args.is_present(ARG_MEMORY_BUDGET) {
+        value_t!(args, ARG_MEMORY_BUDGET, u64)
+            .map_err(|_e| {
+                eprintln!("Use a positive number of MiB as memory-budget. Ignore budget.");
+            })
+            .ok()
+    } else {
+        None
+    };
+
+    let play_length = if let Some(loops) = args.value_of(ARG_LOOPS) {
+        parse_loops(loops).map(PlayLength::LOOPS)
+    } else if let Some(minutes) = args.value_of(ARG_MINUTES) {
+        parse_minutes(minutes).map(PlayLength::DURATION)
+    } else {
+        Some(PlayLength::DURATION(Duration::from_secs(
+            DEFAULT_PLAY_MINUTES * 60,
+        )))
+    }
+    .unwrap_or_else(|| {
+        eprintln!(
+            "Use a positive number as loops or minutes. Show each file for {} minutes.",
+            DEFAULT_PLAY_MINUTES
+        );
+        PlayLength::DURATION(Duration::from_secs(DEFAULT_PLAY_MINUTES * 60))
+    });
+
+    let mut paths = args
+        .values_of(ARG_PATH_TO_GIF)
+        .map(|values| values.map(str::to_owned).collect())
+        .unwrap_or_else(Vec::new);
+
+    let path_to_gif = if paths.is_empty() {
+        String::new()
+    } else {
+        paths.remove(0)
+    };
+
+    let alignment = match args.value_of(ARG_ALIGN).unwrap() {
+        "TOP_LEFT" => Alignment::TOP_LEFT,
+        "TOP" => Alignment::TOP,
+        "TOP_RIGHT" => Alignment::TOP_RIGHT,
+        "LEFT" => Alignment::LEFT,
+        "CENTER" => Alignment::CENTER,
+        "RIGHT" => Alignment::RIGHT,
+        "BOTTOM_LEFT" => Alignment::BOTTOM_LEFT,
+        "BOTTOM" => Alignment::BOTTOM,
+        "BOTTOM_RIGHT" => Alignment::BOTTOM_RIGHT,
+        &_ => Alignment::CENTER, // Cannot happen, due to guarantee of args
+    };
+
+    let background_fill = args.value_of(ARG_BACKGROUND_FILL).and_then(|fill| {
+        BackgroundFill::parse(fill).or_else(|| {
+            eprintln!("Use LINEAR, RADIAL or CHECKERBOARD with two colors as fill. Ignore fill.");
+            None
+        })
+    });
+
+    let crop = args.value_of(ARG_CROP).and_then(|crop| {
+        parse_crop(crop).or_else(|| {
+            eprintln!("Use x,y,w,h in pixels with positive size as crop-region. Ignore crop.");
+            None
+        })
+    });
+
+    let offset = match args.value_of(ARG_OFFSET) {
+        Some(offset) => parse_offset(offset).unwrap_or_else(|| {
+            eprintln!("Use two comma-separated numbers of pixels as offset. Ignore offset.");
+            Offset::default()
+        }),
+        None => Offset::default(),
+    };
+
     let scaling = match args.value_of(ARG_SCALE).unwrap() {
         "NONE" => Scaling::NONE,
         "FILL" => Scaling::FILL,
         "MAX" => Scaling::MAX,
+        "STRETCH" => Scaling::STRETCH,
+        "INTEGER" => Scaling::INTEGER,
+        "TILE" => Scaling::TILE,
+        "MIRROR_TILE" => Scaling::MIRROR_TILE,
         &_ => Scaling::NONE, // Cannot happen, due to guarantee of args
     };
 
-    let scaling_filter = match args.value_of(ARG_SCALE_FILTER).unwrap() {
-        "AUTO" => ScalingFilter::AUTO,
-        "PIXEL" => ScalingFilter::PIXEL,
-        &_ => ScalingFilter::AUTO, // Cannot happen, due to guarantee of args
+    let transition = args
+        .value_of(ARG_TRANSITION)
+        .map(|transition| match transition {
+            "CROSSFADE" => Transition::CROSSFADE,
+            "WIPE" => Transition::WIPE,
+            "SLIDE" => Transition::SLIDE,
+            &_ => Transition::CROSSFADE, // Cannot happen, due to guarantee of args
+        });
+
+    let transition_duration = value_t!(args, ARG_TRANSITION_DURATION, u64)
+        .map(Duration::from_millis)
+        .unwrap_or_else(|_e| {
+            eprintln!(
+                "Use milliseconds as transition-duration. Use {}.",
+                DEFAULT_TRANSITION_DURATION
+            );
+            Duration::from_millis(DEFAULT_TRANSITION_DURATION)
+        });
+
+    let scaling_filter = parse_scaling_filter(args.value_of(ARG_SCALE_FILTER).unwrap());
+    let upscaling_filter = args.value_of(ARG_UPSCALE_FILTER).map(parse_scaling_filter);
+    let downscaling_filter = args
+        .value_of(ARG_DOWNSCALE_FILTER)
+        .map(parse_scaling_filter);
+
+    let target_size = if let Some(size) = args.value_of(ARG_SIZE) {
+        parse_size(size).map(TargetSize::RESOLUTION).or_else(|| {
+            eprintln!(
+                "Use WIDTHxHEIGHT in pixels as size, up to {} each. Ignore size.",
+                MAX_TARGET_SIZE
+            );
+            None
+        })
+    } else if let Some(zoom) = args.value_of(ARG_ZOOM) {
+        parse_zoom(zoom).map(TargetSize::ZOOM).or_else(|| {
+            eprintln!(
+                "Use a positive number up to {} as zoom-factor. Ignore zoom.",
+                MAX_TARGET_SIZE
+            );
+            None
+        })
+    } else {
+        None
     };
 
+    let screen_wallpapers = args
+        .values_of(ARG_SCREEN)
+        .map(|values| values.filter_map(parse_screen_wallpaper).collect())
+        .unwrap_or_default();
+
     Options {
+        alignment,
         background_color: args.value_of(ARG_COLOR).unwrap().to_owned(),
+        background_fill,
+        background_image: args.value_of(ARG_BACKGROUND_IMAGE).map(str::to_owned),
+        clear_cache: args.is_present(ARG_CLEAR_CACHE),
+        crop,
         default_delay: delay,
-        path_to_gif: args.value_of(ARG_PATH_TO_GIF).unwrap().to_owned(),
+        downscaling_filter,
+        frame_delays,
+        linear_light: args.is_present(ARG_LINEAR_LIGHT),
+        memory_budget,
+        no_cache: args.is_present(ARG_NO_CACHE),
+        offset,
+        path_to_gif,
+        play_length,
+        playlist: paths,
+        playlist_file: args.value_of(ARG_PLAYLIST).map(str::to_owned),
         scaling,
         scaling_filter,
+        screen_wallpapers,
+        shuffle: args.is_present(ARG_SHUFFLE),
+        span: args.is_present(ARG_SPAN),
+        target_size,
+        transition,
+        transition_duration,
+        transition_loops: args.is_present(ARG_TRANSITION_LOOPS),
+        upscaling_filter,
         verbose: args.is_present(ARG_VERBOSE),
+        watch: args.is_present(ARG_WATCH),
         window_id: args.value_of(ARG_WINDOW_ID).unwrap_or("").to_string(),
     }
 }
 
+/// Parse name of a scaling-filter, one of `SCALING_FILTERS`.
+fn parse_scaling_filter(filter: &str) -> ScalingFilter {
+    match filter {
+        "AUTO" => ScalingFilter::AUTO,
+        "PIXEL" => ScalingFilter::PIXEL,
+        "TRIANGLE" => ScalingFilter::TRIANGLE,
+        "CATMULL_ROM" => ScalingFilter::CATMULL_ROM,
+        "MITCHELL" => ScalingFilter::MITCHELL,
+        "LANCZOS3" => ScalingFilter::LANCZOS3,
+        "SCALE2X" => ScalingFilter::SCALE2X,
+        "SCALE3X" => ScalingFilter::SCALE3X,
+        &_ => ScalingFilter::AUTO, // Cannot happen, due to guarantee of args
+    }
+}
+
+/// Parse comma-separated list of delays, like `10,20,10`.
+fn parse_frame_delays(delays: &str) -> Result<Vec<u16>, std::num::ParseIntError> {
+    delays
+        .split(',')
+        .map(|delay| delay.trim().parse::<u16>())
+        .collect()
+}
+
+/// Parse crop-region like `10,10,300,200`. Width and height must be at
+/// least 1.
+fn parse_crop(crop: &str) -> Option<Crop> {
+    let values: Vec<u32> = crop
+        .split(',')
+        .map(|value| value.trim().parse().ok())
+        .collect::<Option<_>>()?;
+
+    match values[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Some(Crop::new(x, y, width, height)),
+        _ => None,
+    }
+}
+
+/// Parse offset like `10,-20`.
+fn parse_offset(offset: &str) -> Option<Offset> {
+    let (x, y) = offset.split_once(',')?;
+    Some(Offset::new(x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+/// Parse size like `1280x720`. Both dimensions must be at least 1 and at most
+/// `MAX_TARGET_SIZE`.
+fn parse_size(size: &str) -> Option<Resolution> {
+    let (width, height) = size.split_once('x')?;
+    let width: u32 = width.trim().parse().ok()?;
+    let height: u32 = height.trim().parse().ok()?;
+
+    let is_valid = |length: u32| length > 0 && length <= MAX_TARGET_SIZE;
+
+    if !is_valid(width) || !is_valid(height) {
+        return None;
+    }
+
+    Some(Resolution::new(width, height))
+}
+
+/// Parse a positive zoom-factor like `3` or `0.5`, at most `MAX_TARGET_SIZE`.
+fn parse_zoom(zoom: &str) -> Option<f32> {
+    zoom.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|factor| *factor > 0.0 && *factor <= MAX_TARGET_SIZE as f32)
+}
+
+/// Parse a positive number of loops.
+fn parse_loops(loops: &str) -> Option<u32> {
+    loops.trim().parse().ok().filter(|loops| *loops > 0)
+}
+
+/// Parse a positive number of minutes like `10` or `0.5`, at most a year.
+fn parse_minutes(minutes: &str) -> Option<Duration> {
+    minutes
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|minutes| *minutes > 0.0 && *minutes <= MAX_PLAY_MINUTES)
+        .and_then(|minutes| Duration::try_from_secs_f64(minutes * 60.0).ok())
+}
+
+/// Parse mapping of a screen to a file, like `1=foo.gif` or `+1920+0=foo.gif`.
+fn parse_screen_wallpaper(value: &str) -> Option<ScreenWallpaper> {
+    let parsed = value.split_once('=').and_then(|(selector, path)| {
+        Some(ScreenWallpaper {
+            selector: ScreenSelector::parse(selector)?,
+            path: Some(path).filter(|path| !path.is_empty())?.to_string(),
+        })
+    });
+
+    if parsed.is_none() {
+        eprintln!(
+            "Use SCREEN=PATH with index or origin of screen, like 1=foo.gif or \
+            +1920+0=foo.gif. Ignore {}.",
+            value
+        );
+    }
+
+    parsed
+}
+
 // Test only xgifwallpaper-specifics via arguments. Don't test behaviour of
 // clap, like mandatory fields, valid options or order of arguments.
 #[cfg(test)]
 mod tests {
+    use super::Alignment;
+    use super::BackgroundFill;
+    use super::Crop;
+    use super::Duration;
+    use super::Offset;
     use super::Options;
+    use super::PlayLength;
+    use super::Resolution;
     use super::Scaling;
     use super::ScalingFilter;
+    use super::ScreenSelector;
+    use super::ScreenWallpaper;
+    use super::TargetSize;
+    use super::Transition;
 
     const PATH_TO_GIF: &str = "wallpaper.gif";
 
@@ -179,11 +767,37 @@ mod tests {
     #[test]
     fn use_defaults_for_omitted_arguments() {
         let options = Options::_from_params(_create_params(vec![]));
+        assert_eq!(options.alignment, Alignment::CENTER);
         assert_eq!(options.background_color, "#000000");
+        assert_eq!(options.background_fill, None);
+        assert_eq!(options.background_image, None);
+        assert_eq!(options.clear_cache, false);
+        assert_eq!(options.crop, None);
         assert_eq!(options.default_delay, 10);
+        assert_eq!(options.downscaling_filter, None);
+        assert_eq!(options.frame_delays, Vec::<u16>::new());
+        assert_eq!(options.linear_light, false);
+        assert_eq!(options.memory_budget, None);
+        assert_eq!(options.no_cache, false);
+        assert_eq!(options.offset, Offset::new(0, 0));
+        assert_eq!(
+            options.play_length,
+            PlayLength::DURATION(Duration::from_secs(600))
+        );
+        assert_eq!(options.playlist, Vec::<String>::new());
+        assert_eq!(options.playlist_file, None);
         assert_eq!(options.verbose, false);
         assert_eq!(options.scaling, Scaling::NONE);
         assert_eq!(options.scaling_filter, ScalingFilter::AUTO);
+        assert_eq!(options.screen_wallpapers, vec![]);
+        assert_eq!(options.shuffle, false);
+        assert_eq!(options.span, false);
+        assert_eq!(options.target_size, None);
+        assert_eq!(options.transition, None);
+        assert_eq!(options.transition_duration, Duration::from_millis(1000));
+        assert_eq!(options.transition_loops, false);
+        assert_eq!(options.upscaling_filter, None);
+        assert_eq!(options.watch, false);
     }
 
     #[test]
@@ -202,6 +816,77 @@ mod tests {
         assert_eq!(options.default_delay, 10);
     }
 
+    #[test]
+    fn when_argument_align_is_given_then_match_enum() {
+        let options = Options::_from_params(_create_params(vec!["-a", "TOP_LEFT"]));
+        assert_eq!(options.alignment, Alignment::TOP_LEFT);
+
+        let options = Options::_from_params(_create_params(vec!["--align", "BOTTOM"]));
+        assert_eq!(options.alignment, Alignment::BOTTOM);
+    }
+
+    #[test]
+    fn when_argument_background_fill_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec![
+            "--background-fill",
+            "LINEAR:black,navy,45",
+        ]));
+        assert_eq!(
+            options.background_fill,
+            Some(BackgroundFill::LINEAR {
+                from: "black".to_string(),
+                to: "navy".to_string(),
+                angle: 45.0
+            })
+        );
+    }
+
+    #[test]
+    fn when_argument_background_fill_is_invalid_then_ignore_it() {
+        let options =
+            Options::_from_params(_create_params(vec!["--background-fill", "STRIPES:a,b"]));
+        assert_eq!(options.background_fill, None);
+    }
+
+    #[test]
+    fn when_argument_background_image_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec!["--background-image", "snow.png"]));
+        assert_eq!(options.background_image, Some("snow.png".to_string()));
+    }
+
+    #[test]
+    fn when_argument_crop_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec!["--crop", "10,20,300,200"]));
+        assert_eq!(options.crop, Some(Crop::new(10, 20, 300, 200)));
+    }
+
+    #[test]
+    fn when_argument_crop_is_invalid_then_ignore_it() {
+        let options = Options::_from_params(_create_params(vec!["--crop", "10,20,300"]));
+        assert_eq!(options.crop, None);
+
+        let options = Options::_from_params(_create_params(vec!["--crop", "10,20,0,200"]));
+        assert_eq!(options.crop, None);
+
+        let options = Options::_from_params(_create_params(vec!["--crop", "a,b,c,d"]));
+        assert_eq!(options.crop, None);
+    }
+
+    #[test]
+    fn when_argument_offset_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec!["--offset", "-10,20"]));
+        assert_eq!(options.offset, Offset::new(-10, 20));
+    }
+
+    #[test]
+    fn when_argument_offset_is_invalid_then_ignore_it() {
+        let options = Options::_from_params(_create_params(vec!["--offset", "10"]));
+        assert_eq!(options.offset, Offset::new(0, 0));
+
+        let options = Options::_from_params(_create_params(vec!["--offset", "a,b"]));
+        assert_eq!(options.offset, Offset::new(0, 0));
+    }
+
     #[test]
     fn when_argument_background_color_is_given_then_use_it() {
         let options = Options::_from_params(_create_params(vec!["-b", "white"]));
@@ -214,6 +899,95 @@ mod tests {
         assert_eq!(options.default_delay, 666);
     }
 
+    #[test]
+    fn when_argument_frame_delays_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec!["--frame-delays", "10, 20,5"]));
+        assert_eq!(options.frame_delays, vec![10, 20, 5]);
+    }
+
+    #[test]
+    fn when_argument_frame_delays_is_not_a_list_of_u16_then_ignore_it() {
+        let options = Options::_from_params(_create_params(vec!["--frame-delays", "10,-20"]));
+        assert_eq!(options.frame_delays, Vec::<u16>::new());
+    }
+
+    #[test]
+    fn when_argument_memory_budget_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec!["--memory-budget", "512"]));
+        assert_eq!(options.memory_budget, Some(512));
+    }
+
+    #[test]
+    fn when_argument_memory_budget_is_not_a_number_then_ignore_it() {
+        let options = Options::_from_params(_create_params(vec!["--memory-budget", "a lot"]));
+        assert_eq!(options.memory_budget, None);
+    }
+
+    #[test]
+    fn when_more_paths_are_given_then_play_them_after_first() {
+        let options = Options::_from_params(_create_params(vec!["spring.gif", "summer.gif"]));
+        assert_eq!(options.path_to_gif, "spring.gif");
+        assert_eq!(options.playlist, vec!["summer.gif", PATH_TO_GIF]);
+    }
+
+    #[test]
+    fn when_only_playlist_file_is_given_then_path_to_gif_is_empty() {
+        let options = Options::_from_params(vec![
+            "xgifwallpaper",
+            "--playlist",
+            "seasons.txt",
+            "--shuffle",
+        ]);
+        assert_eq!(options.path_to_gif, "");
+        assert_eq!(options.playlist_file, Some("seasons.txt".to_string()));
+        assert_eq!(options.shuffle, true);
+    }
+
+    #[test]
+    fn when_argument_loops_or_minutes_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec!["--loops", "3"]));
+        assert_eq!(options.play_length, PlayLength::LOOPS(3));
+
+        let options = Options::_from_params(_create_params(vec!["--minutes", "0.5"]));
+        assert_eq!(
+            options.play_length,
+            PlayLength::DURATION(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn when_argument_loops_or_minutes_is_invalid_then_use_default() {
+        let options = Options::_from_params(_create_params(vec!["--loops", "0"]));
+        assert_eq!(
+            options.play_length,
+            PlayLength::DURATION(Duration::from_secs(600))
+        );
+
+        let options = Options::_from_params(_create_params(vec!["--minutes", "0"]));
+        assert_eq!(
+            options.play_length,
+            PlayLength::DURATION(Duration::from_secs(600))
+        );
+
+        let options = Options::_from_params(_create_params(vec!["--minutes", "1e300"]));
+        assert_eq!(
+            options.play_length,
+            PlayLength::DURATION(Duration::from_secs(600))
+        );
+    }
+
+    #[test]
+    fn when_argument_no_cache_is_given_then_be_it() {
+        let options = Options::_from_params(_create_params(vec!["--no-cache"]));
+        assert_eq!(options.no_cache, true);
+    }
+
+    #[test]
+    fn when_argument_clear_cache_is_given_then_be_it() {
+        let options = Options::_from_params(_create_params(vec!["--clear-cache"]));
+        assert_eq!(options.clear_cache, true);
+    }
+
     #[test]
     fn when_argument_verbose_is_given_then_be_it() {
         let options = Options::_from_params(_create_params(vec!["-v"]));
@@ -238,6 +1012,30 @@ mod tests {
         assert_eq!(options.scaling, Scaling::MAX);
     }
 
+    #[test]
+    fn when_argument_scale_is_stretch_then_match_enum() {
+        let options = Options::_from_params(_create_params(vec!["-s", "STRETCH"]));
+        assert_eq!(options.scaling, Scaling::STRETCH);
+    }
+
+    #[test]
+    fn when_argument_scale_is_integer_then_match_enum() {
+        let options = Options::_from_params(_create_params(vec!["-s", "INTEGER"]));
+        assert_eq!(options.scaling, Scaling::INTEGER);
+    }
+
+    #[test]
+    fn when_argument_scale_is_tile_then_match_enum() {
+        let options = Options::_from_params(_create_params(vec!["-s", "TILE"]));
+        assert_eq!(options.scaling, Scaling::TILE);
+    }
+
+    #[test]
+    fn when_argument_scale_is_mirror_tile_then_match_enum() {
+        let options = Options::_from_params(_create_params(vec!["-s", "MIRROR_TILE"]));
+        assert_eq!(options.scaling, Scaling::MIRROR_TILE);
+    }
+
     #[test]
     fn when_argument_scale_filter_is_auto_then_match_enum() {
         let options = Options::_from_params(_create_params(vec!["--scale-filter", "AUTO"]));
@@ -250,6 +1048,157 @@ mod tests {
         assert_eq!(options.scaling_filter, ScalingFilter::PIXEL);
     }
 
+    #[test]
+    fn when_argument_scale_filter_names_other_filter_then_match_enum() {
+        let expected_filters = vec![
+            ("TRIANGLE", ScalingFilter::TRIANGLE),
+            ("CATMULL_ROM", ScalingFilter::CATMULL_ROM),
+            ("MITCHELL", ScalingFilter::MITCHELL),
+            ("LANCZOS3", ScalingFilter::LANCZOS3),
+            ("SCALE2X", ScalingFilter::SCALE2X),
+            ("SCALE3X", ScalingFilter::SCALE3X),
+        ];
+
+        for (name, filter) in expected_filters {
+            let options = Options::_from_params(_create_params(vec!["--scale-filter", name]));
+            assert_eq!(options.scaling_filter, filter);
+        }
+    }
+
+    #[test]
+    fn when_argument_upscale_and_downscale_filter_are_given_then_use_them() {
+        let options = Options::_from_params(_create_params(vec![
+            "--upscale-filter",
+            "PIXEL",
+            "--downscale-filter",
+            "LANCZOS3",
+        ]));
+        assert_eq!(options.scaling_filter, ScalingFilter::AUTO);
+        assert_eq!(options.upscaling_filter, Some(ScalingFilter::PIXEL));
+        assert_eq!(options.downscaling_filter, Some(ScalingFilter::LANCZOS3));
+    }
+
+    #[test]
+    fn when_argument_screen_is_given_then_map_screens_to_files() {
+        let options = Options::_from_params(_create_params(vec![
+            "--screen",
+            "1=one.gif",
+            "--screen",
+            "+1920+0=two.gif",
+        ]));
+
+        assert_eq!(
+            options.screen_wallpapers,
+            vec![
+                ScreenWallpaper {
+                    selector: ScreenSelector::INDEX(1),
+                    path: "one.gif".to_string()
+                },
+                ScreenWallpaper {
+                    selector: ScreenSelector::POSITION(1920, 0),
+                    path: "two.gif".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn when_argument_screen_is_invalid_then_ignore_it() {
+        let options = Options::_from_params(_create_params(vec![
+            "--screen",
+            "one.gif",
+            "--screen",
+            "left=one.gif",
+            "--screen",
+            "1=",
+        ]));
+
+        assert_eq!(options.screen_wallpapers, vec![]);
+    }
+
+    #[test]
+    fn when_argument_linear_light_is_given_then_be_it() {
+        let options = Options::_from_params(_create_params(vec!["--linear-light"]));
+        assert_eq!(options.linear_light, true);
+    }
+
+    #[test]
+    fn when_argument_span_is_given_then_be_it() {
+        let options = Options::_from_params(_create_params(vec!["--span"]));
+        assert_eq!(options.span, true);
+    }
+
+    #[test]
+    fn when_argument_size_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec!["--size", "1280x720"]));
+        assert_eq!(
+            options.target_size,
+            Some(TargetSize::RESOLUTION(Resolution::new(1280, 720)))
+        );
+    }
+
+    #[test]
+    fn when_argument_size_is_invalid_then_ignore_it() {
+        let options = Options::_from_params(_create_params(vec!["--size", "1280"]));
+        assert_eq!(options.target_size, None);
+
+        let options = Options::_from_params(_create_params(vec!["--size", "0x720"]));
+        assert_eq!(options.target_size, None);
+
+        let options = Options::_from_params(_create_params(vec!["--size", "16385x720"]));
+        assert_eq!(options.target_size, None);
+    }
+
+    #[test]
+    fn when_argument_zoom_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec!["--zoom", "3"]));
+        assert_eq!(options.target_size, Some(TargetSize::ZOOM(3.0)));
+
+        let options = Options::_from_params(_create_params(vec!["--zoom", "0.5"]));
+        assert_eq!(options.target_size, Some(TargetSize::ZOOM(0.5)));
+    }
+
+    #[test]
+    fn when_argument_zoom_is_not_a_positive_number_then_ignore_it() {
+        let options = Options::_from_params(_create_params(vec!["--zoom", "0"]));
+        assert_eq!(options.target_size, None);
+
+        let options = Options::_from_params(_create_params(vec!["--zoom", "abc"]));
+        assert_eq!(options.target_size, None);
+
+        let options = Options::_from_params(_create_params(vec!["--zoom", "inf"]));
+        assert_eq!(options.target_size, None);
+
+        let options = Options::_from_params(_create_params(vec!["--zoom", "1e9"]));
+        assert_eq!(options.target_size, None);
+    }
+
+    #[test]
+    fn when_argument_transition_is_given_then_use_it() {
+        let options = Options::_from_params(_create_params(vec![
+            "--transition",
+            "SLIDE",
+            "--transition-duration",
+            "250",
+            "--transition-loops",
+        ]));
+        assert_eq!(options.transition, Some(Transition::SLIDE));
+        assert_eq!(options.transition_duration, Duration::from_millis(250));
+        assert_eq!(options.transition_loops, true);
+    }
+
+    #[test]
+    fn when_argument_transition_duration_is_invalid_then_use_default() {
+        let options = Options::_from_params(_create_params(vec!["--transition-duration", "1s"]));
+        assert_eq!(options.transition_duration, Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn when_argument_watch_is_given_then_be_it() {
+        let options = Options::_from_params(_create_params(vec!["--watch"]));
+        assert_eq!(options.watch, true);
+    }
+
     #[test]
     fn when_argument_window_id_is_given_then_use_it() {
         let options = Options::_from_params(_create_params(vec!["-w", "foobar"]));
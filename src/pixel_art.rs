@@ -0,0 +1,282 @@
+//! Upscalers for pixel-art, which smooth diagonal edges without blurring:
+//! Scale2x and Scale3x, also known as AdvMAME2x and AdvMAME3x.
+//!
+//! Pixels are compared for equality only, so rasters may have any type of
+//! channel, premultiplied or not.
+
+use crate::position::Resolution;
+
+const CHANNELS: usize = 4;
+
+/// Enlarges the raster by `factor` of 2 or 3, as often as needed to cover
+/// the target-resolution in width or height. Returns the enlarged raster and
+/// its resolution, which may exceed the target. Stopping at the first covered
+/// side keeps targets of another aspect-ratio from growing the other one.
+pub fn upscale<T: Copy + PartialEq>(
+    raster: Vec<T>,
+    image_resolution: &Resolution,
+    target_resolution: &Resolution,
+    factor: u32,
+) -> (Vec<T>, Resolution) {
+    let mut raster = raster;
+    let mut resolution = image_resolution.clone();
+
+    while resolution.width < target_resolution.width && resolution.height < target_resolution.height
+    {
+        raster = match factor {
+            3 => scale3x(&raster, &resolution),
+            _ => scale2x(&raster, &resolution),
+        };
+        resolution = Resolution::new(resolution.width * factor, resolution.height * factor);
+    }
+
+    (raster, resolution)
+}
+
+/// Enlarges each pixel to 2x2 pixels. Corners take the color of both
+/// neighbors, if these are equal and form a diagonal edge.
+pub fn scale2x<T: Copy + PartialEq>(raster: &[T], resolution: &Resolution) -> Vec<T> {
+    let image = Image::new(raster, resolution);
+    let mut scaled: Vec<T> = Vec::with_capacity(raster.len() * 4);
+
+    for y in 0..image.height {
+        let mut lower_row: Vec<T> = Vec::with_capacity(image.width * 2 * CHANNELS);
+
+        for x in 0..image.width {
+            let p = image.pixel(x, y);
+            let a = image.neighbor(x, y, 0, -1);
+            let b = image.neighbor(x, y, 1, 0);
+            let c = image.neighbor(x, y, -1, 0);
+            let d = image.neighbor(x, y, 0, 1);
+
+            let e0 = if c == a && c != d && a != b { a } else { p };
+            let e1 = if a == b && a != c && b != d { b } else { p };
+            let e2 = if d == c && d != b && c != a { c } else { p };
+            let e3 = if b == d && b != a && d != c { d } else { p };
+
+            scaled.extend_from_slice(e0);
+            scaled.extend_from_slice(e1);
+            lower_row.extend_from_slice(e2);
+            lower_row.extend_from_slice(e3);
+        }
+
+        scaled.extend(lower_row);
+    }
+
+    scaled
+}
+
+/// Enlarges each pixel to 3x3 pixels. Corners and edges take the color of
+/// neighbors, if these form a diagonal edge.
+pub fn scale3x<T: Copy + PartialEq>(raster: &[T], resolution: &Resolution) -> Vec<T> {
+    let image = Image::new(raster, resolution);
+    let mut scaled: Vec<T> = Vec::with_capacity(raster.len() * 9);
+
+    for y in 0..image.height {
+        let mut rows: [Vec<T>; 3] = [Vec::new(), Vec::new(), Vec::new()];
+
+        for x in 0..image.width {
+            let a = image.neighbor(x, y, -1, -1);
+            let b = image.neighbor(x, y, 0, -1);
+            let c = image.neighbor(x, y, 1, -1);
+            let d = image.neighbor(x, y, -1, 0);
+            let e = image.pixel(x, y);
+            let f = image.neighbor(x, y, 1, 0);
+            let g = image.neighbor(x, y, -1, 1);
+            let h = image.neighbor(x, y, 0, 1);
+            let i = image.neighbor(x, y, 1, 1);
+
+            let top_left = d == b && d != h && b != f;
+            let top_right = b == f && b != d && f != h;
+            let bottom_left = d == h && d != b && h != f;
+            let bottom_right = h == f && h != d && f != b;
+
+            let block = [
+                [
+                    if top_left { d } else { e },
+                    if (top_left && e != c) || (top_right && e != a) {
+                        b
+                    } else {
+                        e
+                    },
+                    if top_right { f } else { e },
+                ],
+                [
+                    if (top_left && e != g) || (bottom_left && e != a) {
+                        d
+                    } else {
+                        e
+                    },
+                    e,
+                    if (top_right && e != i) || (bottom_right && e != c) {
+                        f
+                    } else {
+                        e
+                    },
+                ],
+                [
+                    if bottom_left { d } else { e },
+                    if (bottom_left && e != i) || (bottom_right && e != g) {
+                        h
+                    } else {
+                        e
+                    },
+                    if bottom_right { f } else { e },
+                ],
+            ];
+
+            for (row, pixels) in rows.iter_mut().zip(block.iter()) {
+                for pixel in pixels {
+                    row.extend_from_slice(pixel);
+                }
+            }
+        }
+
+        for row in rows.iter() {
+            scaled.extend_from_slice(row);
+        }
+    }
+
+    scaled
+}
+
+/// Raster with access to pixels and their neighbors. Neighbors beyond the
+/// edges repeat the edge.
+struct Image<'a, T> {
+    raster: &'a [T],
+    width: usize,
+    height: usize,
+}
+
+impl<'a, T> Image<'a, T> {
+    fn new(raster: &'a [T], resolution: &Resolution) -> Image<'a, T> {
+        Image {
+            raster,
+            width: resolution.width as usize,
+            height: resolution.height as usize,
+        }
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> &'a [T] {
+        let start = (y * self.width + x) * CHANNELS;
+        &self.raster[start..start + CHANNELS]
+    }
+
+    fn neighbor(&self, x: usize, y: usize, dx: isize, dy: isize) -> &'a [T] {
+        let x = (x as isize + dx).max(0).min(self.width as isize - 1) as usize;
+        let y = (y as isize + dy).max(0).min(self.height as isize - 1) as usize;
+
+        self.pixel(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scale2x, scale3x, upscale};
+    use crate::position::Resolution;
+
+    #[test]
+    fn when_pixel_is_scaled_2x_then_it_is_repeated() {
+        let actual = scale2x(&_raster(&["X"]), &Resolution::new(1, 1));
+        assert_eq!(_rows(&actual, 2), vec!["XX", "XX"]);
+    }
+
+    #[test]
+    fn when_diagonal_is_scaled_2x_then_smooth_its_edges() {
+        let actual = scale2x(&_raster(&["X.", ".X"]), &Resolution::new(2, 2));
+        assert_eq!(_rows(&actual, 4), vec!["XX..", "X.X.", ".X.X", "..XX"]);
+    }
+
+    #[test]
+    fn when_straight_edge_is_scaled_2x_then_keep_it_straight() {
+        let actual = scale2x(&_raster(&["XX", ".."]), &Resolution::new(2, 2));
+        assert_eq!(_rows(&actual, 4), vec!["XXXX", "XXXX", "....", "...."]);
+    }
+
+    #[test]
+    fn when_pixel_is_scaled_3x_then_it_is_repeated() {
+        let actual = scale3x(&_raster(&["X"]), &Resolution::new(1, 1));
+        assert_eq!(_rows(&actual, 3), vec!["XXX", "XXX", "XXX"]);
+    }
+
+    #[test]
+    fn when_diagonal_is_scaled_3x_then_smooth_its_edges() {
+        let actual = scale3x(&_raster(&["X.", ".X"]), &Resolution::new(2, 2));
+        assert_eq!(
+            _rows(&actual, 6),
+            vec!["XXX...", "XX.X..", "X..XX.", ".XX..X", "..X.XX", "...XXX"]
+        );
+    }
+
+    #[test]
+    fn when_straight_edge_is_scaled_3x_then_keep_it_straight() {
+        let actual = scale3x(&_raster(&["X.", "X."]), &Resolution::new(2, 2));
+        assert_eq!(
+            _rows(&actual, 6),
+            vec!["XXX...", "XXX...", "XXX...", "XXX...", "XXX...", "XXX..."]
+        );
+    }
+
+    #[test]
+    fn when_upscaled_then_repeat_until_target_is_covered() {
+        let (raster, resolution) = upscale(
+            _raster(&["X"]),
+            &Resolution::new(1, 1),
+            &Resolution::new(5, 5),
+            2,
+        );
+
+        assert_eq!(resolution, Resolution::new(8, 8));
+        assert_eq!(raster.len(), 8 * 8 * 4);
+
+        let (_, resolution) = upscale(
+            _raster(&["X"]),
+            &Resolution::new(1, 1),
+            &Resolution::new(5, 5),
+            3,
+        );
+
+        assert_eq!(resolution, Resolution::new(9, 9));
+    }
+
+    #[test]
+    fn when_target_has_other_aspect_ratio_then_stop_once_a_side_is_covered() {
+        let (raster, resolution) = upscale(
+            vec![0u8; 10 * 4],
+            &Resolution::new(10, 1),
+            &Resolution::new(1920, 1080),
+            2,
+        );
+
+        assert_eq!(resolution, Resolution::new(2560, 256));
+        assert_eq!(raster.len(), 2560 * 256 * 4);
+    }
+
+    #[test]
+    fn when_target_is_covered_already_then_keep_raster() {
+        let (raster, resolution) = upscale(
+            _raster(&["X.", ".X"]),
+            &Resolution::new(2, 2),
+            &Resolution::new(1, 2),
+            2,
+        );
+
+        assert_eq!(resolution, Resolution::new(2, 2));
+        assert_eq!(raster, _raster(&["X.", ".X"]));
+    }
+
+    /// Raster with a pixel per character, all channels set to its code.
+    fn _raster(rows: &[&str]) -> Vec<u8> {
+        rows.iter()
+            .flat_map(|row| row.bytes())
+            .flat_map(|c| vec![c; 4])
+            .collect()
+    }
+
+    fn _rows(raster: &[u8], width: usize) -> Vec<String> {
+        raster
+            .chunks_exact(width * 4)
+            .map(|row| row.chunks_exact(4).map(|pixel| pixel[0] as char).collect())
+            .collect()
+    }
+}
@@ -0,0 +1,328 @@
+//! Files to show in turn, each for a number of loops or minutes, in order
+//! given or shuffled.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::options::Options;
+
+/// Still images are shown this long, if files are played by loops.
+const STILL_DURATION: Duration = Duration::from_secs(60);
+
+/// How long each file of a playlist is shown.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlayLength {
+    /// Number of loops of the animation. If a file is shown on multiple
+    /// screens, the least played one counts.
+    LOOPS(u32),
+    DURATION(Duration),
+}
+
+impl PlayLength {
+    /// `true`, if a file shown since `shown_at` was played long enough at
+    /// `now`. `loops` are those of the least played animation, `None` if all
+    /// are still images.
+    pub fn is_over(&self, shown_at: Instant, loops: Option<u32>, now: Instant) -> bool {
+        match (self, loops) {
+            (PlayLength::LOOPS(length), Some(loops)) => loops >= *length,
+            _ => self
+                .deadline(shown_at, loops)
+                .map_or(false, |due| due <= now),
+        }
+    }
+
+    /// Point of time to show the next file, if played by time. `None` as well,
+    /// if too far ahead to be represented.
+    pub fn deadline(&self, shown_at: Instant, loops: Option<u32>) -> Option<Instant> {
+        match (self, loops) {
+            (PlayLength::LOOPS(_), Some(_)) => None,
+            (PlayLength::LOOPS(_), None) => shown_at.checked_add(STILL_DURATION),
+            (PlayLength::DURATION(length), _) => shown_at.checked_add(*length),
+        }
+    }
+}
+
+/// Paths of files to show in turn. Shuffled ones are played in a new order
+/// each round, without showing the same file twice in a row.
+pub struct Playlist {
+    /// Files given as arguments, followed by those of `playlist_file`.
+    paths: Vec<String>,
+    given: Vec<String>,
+    playlist_file: Option<String>,
+    current: String,
+    /// Indices of the next paths, refilled by a round when empty.
+    queue: VecDeque<usize>,
+    shuffle: bool,
+    /// State of the xorshift-generator to shuffle with. Never 0.
+    seed: u64,
+}
+
+impl Playlist {
+    /// Collects the files given as arguments, followed by those of the
+    /// playlist-file.
+    pub fn open(options: &Options) -> Result<Playlist, String> {
+        let mut given: Vec<String> = Vec::new();
+
+        if !options.path_to_gif.is_empty() {
+            given.push(options.path_to_gif.clone());
+        }
+
+        given.extend(options.playlist.iter().cloned());
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        let mut playlist = Playlist::new(given, options.shuffle, seed);
+        playlist.playlist_file = options.playlist_file.clone();
+        playlist.reload()?;
+
+        Ok(playlist)
+    }
+
+    fn new(given: Vec<String>, shuffle: bool, seed: u64) -> Playlist {
+        let mut playlist = Playlist {
+            paths: given.clone(),
+            given,
+            playlist_file: None,
+            // None yet, the first one is taken from the queue
+            current: String::new(),
+            queue: VecDeque::new(),
+            shuffle,
+            seed: seed | 1,
+        };
+
+        if !playlist.paths.is_empty() {
+            playlist.advance();
+        }
+
+        playlist
+    }
+
+    /// Reads the playlist-file again, if any. The current file stays shown,
+    /// the next ones are those of the new playlist.
+    pub fn reload(&mut self) -> Result<(), String> {
+        let mut paths = self.given.clone();
+
+        if let Some(playlist_file) = &self.playlist_file {
+            paths.extend(read_playlist_file(playlist_file)?);
+        }
+
+        if paths.is_empty() {
+            return Err("Playlist has no files to show".to_string());
+        }
+
+        self.paths = paths;
+        self.queue.clear();
+
+        match self.paths.iter().position(|path| *path == self.current) {
+            // Go on in order after the current file
+            Some(index) if !self.shuffle => self.queue.extend(index + 1..self.paths.len()),
+            Some(_) => {}
+            None if self.current.is_empty() => {
+                self.advance();
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Files of the playlist, in order given.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Path of the file shown now.
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// Path of the file to show next.
+    pub fn upcoming(&mut self) -> &str {
+        if self.queue.is_empty() {
+            self.refill();
+        }
+
+        &self.paths[self.queue[0]]
+    }
+
+    /// Moves on to the upcoming file.
+    pub fn advance(&mut self) -> &str {
+        self.upcoming();
+
+        let index = self.queue.pop_front().unwrap();
+        self.current = self.paths[index].clone();
+
+        self.current()
+    }
+
+    /// Queues all files for another round.
+    fn refill(&mut self) {
+        let mut round: Vec<usize> = (0..self.paths.len()).collect();
+
+        if self.shuffle {
+            // Fisher-Yates
+            for index in (1..round.len()).rev() {
+                round.swap(index, (self.random() % (index as u64 + 1)) as usize);
+            }
+
+            // Don't show the current file again
+            if round.len() > 1 && self.paths[round[0]] == self.current {
+                round.swap(0, 1);
+            }
+        }
+
+        self.queue.extend(round);
+    }
+
+    /// Next number of the xorshift-generator.
+    fn random(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+
+        self.seed
+    }
+}
+
+/// Reads paths of a playlist-file, one per line. Empty lines and lines
+/// starting with `#` are skipped. Relative paths are relative to the
+/// directory of the playlist-file.
+pub fn read_playlist_file(path: &str) -> Result<Vec<String>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Unable to read playlist {}: {}", path, e))?;
+
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+
+    Ok(parse_playlist(&content, directory))
+}
+
+fn parse_playlist(content: &str, directory: &Path) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| directory.join(line).to_string_lossy().into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_playlist, PlayLength, Playlist};
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn when_not_shuffled_then_play_in_order_and_start_over() {
+        let mut playlist = Playlist::new(_paths(3), false, 42);
+
+        assert_eq!(playlist.current(), "0.gif");
+        assert_eq!(playlist.upcoming(), "1.gif");
+        assert_eq!(playlist.advance(), "1.gif");
+        assert_eq!(playlist.advance(), "2.gif");
+        assert_eq!(playlist.advance(), "0.gif");
+    }
+
+    #[test]
+    fn when_shuffled_then_play_each_file_once_per_round() {
+        let mut playlist = Playlist::new(_paths(5), true, 42);
+
+        let mut played = vec![playlist.current().to_string()];
+        for _ in 1..50 {
+            played.push(playlist.advance().to_string());
+        }
+
+        for round in played.chunks(5) {
+            assert_eq!(round.iter().collect::<HashSet<_>>().len(), 5);
+        }
+
+        assert!(
+            played.windows(2).all(|pair| pair[0] != pair[1]),
+            "Same file twice in a row"
+        );
+        assert_ne!(played[0..5], played[5..10], "Each round is shuffled");
+    }
+
+    #[test]
+    fn when_playlist_has_single_file_then_repeat_it() {
+        let mut playlist = Playlist::new(_paths(1), true, 42);
+
+        assert_eq!(playlist.advance(), "0.gif");
+        assert_eq!(playlist.advance(), "0.gif");
+    }
+
+    #[test]
+    fn when_playlist_file_is_reloaded_then_go_on_after_current_file() {
+        let path = std::env::temp_dir().join(format!("xgifwallpaper-{}.txt", std::process::id()));
+        fs::write(&path, "a.gif\nb.gif\nc.gif\n").unwrap();
+
+        let mut playlist = Playlist::new(vec![], false, 42);
+        playlist.playlist_file = Some(path.to_string_lossy().into_owned());
+        playlist.reload().unwrap();
+
+        assert!(playlist.advance().ends_with("b.gif"));
+
+        fs::write(&path, "b.gif\nd.gif\n").unwrap();
+        playlist.reload().unwrap();
+
+        assert!(playlist.current().ends_with("b.gif"), "Current file stays");
+        assert!(playlist.advance().ends_with("d.gif"));
+        assert_eq!(playlist.len(), 2);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn when_playlist_file_is_parsed_then_skip_comments_and_resolve_paths() {
+        let content = "# Winter\nsnow.gif\n\n  /abs/ice.gif  \n";
+
+        assert_eq!(
+            parse_playlist(content, Path::new("seasons")),
+            vec!["seasons/snow.gif".to_string(), "/abs/ice.gif".to_string()]
+        );
+    }
+
+    #[test]
+    fn when_played_by_duration_then_over_after_it() {
+        let shown_at = Instant::now();
+        let length = PlayLength::DURATION(MINUTE * 5);
+
+        assert!(!length.is_over(shown_at, Some(100), shown_at + MINUTE));
+        assert!(length.is_over(shown_at, Some(0), shown_at + MINUTE * 5));
+        assert_eq!(length.deadline(shown_at, None), Some(shown_at + MINUTE * 5));
+    }
+
+    #[test]
+    fn when_played_by_loops_then_over_after_least_played_one() {
+        let shown_at = Instant::now();
+        let length = PlayLength::LOOPS(3);
+
+        assert!(!length.is_over(shown_at, Some(2), shown_at + MINUTE * 100));
+        assert!(length.is_over(shown_at, Some(3), shown_at));
+        assert_eq!(length.deadline(shown_at, Some(2)), None);
+    }
+
+    #[test]
+    fn when_played_by_loops_then_still_images_are_shown_a_minute() {
+        let shown_at = Instant::now();
+        let length = PlayLength::LOOPS(3);
+
+        assert!(!length.is_over(shown_at, None, shown_at));
+        assert!(length.is_over(shown_at, None, shown_at + MINUTE));
+    }
+
+    fn _paths(count: usize) -> Vec<String> {
+        (0..count).map(|index| format!("{}.gif", index)).collect()
+    }
+}
@@ -0,0 +1,94 @@
+//! Minimal pool of worker-threads for CPU-heavy jobs, like scaling frames.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::sync_channel;
+use std::thread;
+
+/// Runs `job` for every index in `0..count` on all available cores. Each
+/// result is handed to `consume` on the calling thread, in order of
+/// completion. Stops taking new jobs, if `running` turns `false`.
+pub fn for_each_parallel<T, J, C>(count: usize, running: &AtomicBool, job: J, mut consume: C)
+where
+    T: Send,
+    J: Fn(usize) -> T + Sync,
+    C: FnMut(usize, T),
+{
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(count.max(1));
+
+    let next_job = AtomicUsize::new(0);
+    let (sender, receiver) = sync_channel::<(usize, T)>(workers);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let next_job = &next_job;
+            let job = &job;
+
+            scope.spawn(move || loop {
+                let index = next_job.fetch_add(1, Ordering::SeqCst);
+
+                if index >= count || !running.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                if sender.send((index, job(index))).is_err() {
+                    break;
+                }
+            });
+        }
+
+        // Receiving ends, when all workers are done and dropped their sender
+        drop(sender);
+
+        for (index, result) in receiver {
+            consume(index, result);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::for_each_parallel;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn when_jobs_are_done_then_consume_every_result_once() {
+        let running = AtomicBool::new(true);
+        let mut results: Vec<Option<usize>> = vec![None; 100];
+
+        for_each_parallel(
+            100,
+            &running,
+            |i| i * 2,
+            |i, result| {
+                assert_eq!(results[i], None);
+                results[i] = Some(result);
+            },
+        );
+
+        assert!(results.iter().enumerate().all(|(i, r)| *r == Some(i * 2)));
+    }
+
+    #[test]
+    fn when_there_are_no_jobs_then_consume_nothing() {
+        let running = AtomicBool::new(true);
+        let mut consumed = 0;
+
+        for_each_parallel(0, &running, |i| i, |_, _| consumed += 1);
+
+        assert_eq!(consumed, 0);
+    }
+
+    #[test]
+    fn when_not_running_then_take_no_jobs() {
+        let running = AtomicBool::new(false);
+        let mut consumed = 0;
+
+        for_each_parallel(100, &running, |i| i, |_, _| consumed += 1);
+
+        assert_eq!(consumed, 0);
+    }
+}
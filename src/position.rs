@@ -2,14 +2,112 @@
 //! and options for placement and scaling.
 use crate::screens::*;
 
-/// Alignments of 2-dimensional rectangles.
+/// Alignments of 2-dimensional rectangles, relative to parent.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Alignment {
-    /// Center horizontally and vertically, relative to parent.
+    TOP_LEFT,
+    /// Top edge, centered horizontally.
+    TOP,
+    TOP_RIGHT,
+    /// Left edge, centered vertically.
+    LEFT,
+    /// Center horizontally and vertically.
     CENTER,
+    /// Right edge, centered vertically.
+    RIGHT,
+    BOTTOM_LEFT,
+    /// Bottom edge, centered horizontally.
+    BOTTOM,
+    BOTTOM_RIGHT,
 }
 
-/// Scaling-options. All options respect aspect-ratio.
-#[derive(Debug, PartialEq, Eq)]
+/// Alignment on a single axis.
+#[derive(Clone, Copy)]
+enum AxisAlignment {
+    START,
+    CENTER,
+    END,
+}
+
+impl Alignment {
+    /// Alignment on the horizontal and vertical axis.
+    fn on_axes(&self) -> (AxisAlignment, AxisAlignment) {
+        use AxisAlignment::*;
+
+        match *self {
+            Alignment::TOP_LEFT => (START, START),
+            Alignment::TOP => (CENTER, START),
+            Alignment::TOP_RIGHT => (END, START),
+            Alignment::LEFT => (START, CENTER),
+            Alignment::CENTER => (CENTER, CENTER),
+            Alignment::RIGHT => (END, CENTER),
+            Alignment::BOTTOM_LEFT => (START, END),
+            Alignment::BOTTOM => (CENTER, END),
+            Alignment::BOTTOM_RIGHT => (END, END),
+        }
+    }
+}
+
+/// Distance in pixels to move an image from its aligned position. Positive
+/// values move right and down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Offset {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Offset {
+    pub fn new(x: i32, y: i32) -> Offset {
+        Offset { x, y }
+    }
+}
+
+/// Rectangle to cut out of an image, before it is scaled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Crop {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Crop {
+        Crop {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Clips the region to the part within the image. `None`, if it lies
+    /// outside of the image.
+    pub fn clip_to_image(&self, image_resolution: &Resolution) -> Option<Crop> {
+        if self.x >= image_resolution.width || self.y >= image_resolution.height {
+            return None;
+        }
+
+        let width = self.width.min(image_resolution.width - self.x);
+        let height = self.height.min(image_resolution.height - self.y);
+
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        Some(Crop::new(self.x, self.y, width, height))
+    }
+
+    /// Resolution of the cut out image.
+    pub fn resolution(&self) -> Resolution {
+        Resolution::new(self.width, self.height)
+    }
+}
+
+/// Scaling-options. All options, except `STRETCH`, respect aspect-ratio.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Scaling {
     /// Don't scale
     NONE,
@@ -17,15 +115,59 @@ pub enum Scaling {
     FILL,
     /// Be as large as possible, without losing content.
     MAX,
+    /// Fill the whole screen exactly, ignoring aspect-ratio.
+    STRETCH,
+    /// Multiply by the largest whole number, without losing content. So each
+    /// pixel has the same size, like for pixel-art. Never scales down.
+    INTEGER,
+    /// Don't scale, but repeat the image to cover the whole screen.
+    TILE,
+    /// Like `TILE`, but every other tile is mirrored, so edges meet seamlessly.
+    /// The image is rendered with its mirrored copies as a single tile of
+    /// double width and height.
+    MIRROR_TILE,
+}
+
+impl Scaling {
+    /// `true`, if the image is repeated to cover the screen.
+    pub fn is_tiled(&self) -> bool {
+        *self == Scaling::TILE || *self == Scaling::MIRROR_TILE
+    }
+}
+
+/// Largest width or height of an explicit size, in pixels.
+pub const MAX_TARGET_SIZE: u32 = 16384;
+
+/// Explicit size of an image, instead of scaling it relative to the screen.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TargetSize {
+    /// Multiply width and height by a factor.
+    ZOOM(f32),
+    /// Scale to exactly this resolution.
+    RESOLUTION(Resolution),
 }
 
 /// Filter to use for scaling.
-#[derive(Debug, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ScalingFilter {
     /// Use best-quality for up- and down-scaling.
     AUTO,
     /// Use most simple and perfomant filter.
     PIXEL,
+    /// Linear interpolation, slightly blurry.
+    TRIANGLE,
+    /// Cubic interpolation, sharper than `MITCHELL`.
+    CATMULL_ROM,
+    /// Cubic interpolation, balancing sharpness and ringing.
+    MITCHELL,
+    /// Sharpest filter, may ring at hard edges.
+    LANCZOS3,
+    /// Pixel-art upscaler, enlarging each pixel to 2x2 pixels as often as
+    /// needed, smoothing diagonal edges.
+    SCALE2X,
+    /// Pixel-art upscaler, like `SCALE2X` but with 3x3 pixels.
+    SCALE3X,
 }
 
 /// Coordinates to place an image.
@@ -63,6 +205,28 @@ impl ImagePlacement {
             height,
         }
     }
+
+    /// Clips the placement to its visible part on a screen. `None`, if no part
+    /// is visible.
+    pub fn clip_to_screen(&self, screen: &Screen) -> Option<ImagePlacement> {
+        let left = self.dest_x.max(screen.x_org);
+        let top = self.dest_y.max(screen.y_org);
+        let right = (self.dest_x + self.width as i32).min(screen.x_org + screen.width as i32);
+        let bottom = (self.dest_y + self.height as i32).min(screen.y_org + screen.height as i32);
+
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        Some(ImagePlacement::new(
+            self.src_x + (left - self.dest_x),
+            self.src_y + (top - self.dest_y),
+            left,
+            top,
+            (right - left) as u32,
+            (bottom - top) as u32,
+        ))
+    }
 }
 
 /// Width and height as one unit.
@@ -84,6 +248,52 @@ impl Resolution {
             Scaling::NONE => self.clone(),
             Scaling::FILL => Resolution::scale_image_to_screen(self, screen_resolution, true),
             Scaling::MAX => Resolution::scale_image_to_screen(self, screen_resolution, false),
+            Scaling::STRETCH => screen_resolution.clone(),
+            Scaling::INTEGER => Resolution::scale_image_by_integer(self, screen_resolution),
+            Scaling::TILE => self.clone(),
+            Scaling::MIRROR_TILE => Resolution::new(self.width * 2, self.height * 2),
+        }
+    }
+
+    /// Calculates the resolution an image should have for an explicit size, if
+    /// given. Otherwise to respect given scaling. Tiling-modes still repeat
+    /// the image in its explicit size. Mirrored tiles hold the image twice in
+    /// each direction, so it is limited to half of `MAX_TARGET_SIZE`.
+    pub fn fit_to_target(
+        &self,
+        screen_resolution: &Resolution,
+        scaling: &Scaling,
+        size: &Option<TargetSize>,
+    ) -> Resolution {
+        match size {
+            None => self.fit_to_screen(screen_resolution, scaling),
+            Some(size) if *scaling == Scaling::MIRROR_TILE => self
+                .resize_to(size, MAX_TARGET_SIZE / 2)
+                .fit_to_screen(screen_resolution, scaling),
+            Some(size) if scaling.is_tiled() => self
+                .resize_to(size, MAX_TARGET_SIZE)
+                .fit_to_screen(screen_resolution, scaling),
+            Some(size) => self.resize_to(size, MAX_TARGET_SIZE),
+        }
+    }
+
+    /// Calculates the resolution of the image in explicit size. At least a
+    /// single pixel, at most `limit` pixels per side. A zoomed image keeps its
+    /// aspect-ratio.
+    fn resize_to(&self, size: &TargetSize, limit: u32) -> Resolution {
+        match size {
+            TargetSize::ZOOM(factor) => {
+                let longest = self.width.max(self.height).max(1) as f32;
+                let factor = factor.min(limit as f32 / longest);
+
+                Resolution::new(
+                    ((self.width as f32 * factor).round() as u32).max(1),
+                    ((self.height as f32 * factor).round() as u32).max(1),
+                )
+            }
+            TargetSize::RESOLUTION(resolution) => {
+                Resolution::new(resolution.width.min(limit), resolution.height.min(limit))
+            }
         }
     }
 
@@ -132,47 +342,148 @@ impl Resolution {
         result
     }
 
-    /// Computes coordinates of image for given alignment on a screen.
-    pub fn position_on_screen(&self, screen: &Screen, alignment: Alignment) -> ImagePlacement {
-        match alignment {
-            Alignment::CENTER => Resolution::center_on_screen(self.width, self.height, screen),
-        }
+    /// Calculates the resolution of the image multiplied by the largest whole
+    /// number, which fits on the screen. At least the image-resolution.
+    fn scale_image_by_integer(
+        image_resolution: &Resolution,
+        screen_resolution: &Resolution,
+    ) -> Resolution {
+        let factor = (screen_resolution.width / image_resolution.width.max(1))
+            .min(screen_resolution.height / image_resolution.height.max(1))
+            .max(1);
+
+        Resolution::new(
+            image_resolution.width * factor,
+            image_resolution.height * factor,
+        )
     }
 
-    /// Places an image on screen, aligned to the center. Both horizontally and
-    /// vertically.
-    fn center_on_screen(width: u32, height: u32, screen: &Screen) -> ImagePlacement {
-        let mut out = ImagePlacement::new(0, 0, screen.x_org, screen.y_org, width, height);
+    /// Computes coordinates of image for given alignment on a screen, moved
+    /// by offset. Parts of the image beyond the screen are cut off.
+    pub fn position_on_screen(
+        &self,
+        screen: &Screen,
+        alignment: Alignment,
+        offset: Offset,
+    ) -> ImagePlacement {
+        let (horizontal, vertical) = alignment.on_axes();
 
-        if width > screen.width {
-            out.src_x = ((width - screen.width) / 2) as i32;
-            out.width = screen.width;
-        }
+        let (src_x, dest_x, width) =
+            align_on_axis(self.width, screen.x_org, screen.width, horizontal, offset.x);
+        let (src_y, dest_y, height) =
+            align_on_axis(self.height, screen.y_org, screen.height, vertical, offset.y);
 
-        if height > screen.height {
-            out.src_y = ((height - screen.height) / 2) as i32;
-            out.height = screen.height;
-        }
+        ImagePlacement::new(src_x, src_y, dest_x, dest_y, width, height)
+    }
+
+    /// Computes coordinates of all tiles, to cover the screen with repetitions
+    /// of the image. One tile is aligned on the screen and moved by offset,
+    /// the others are lined up around it. Tiles at the edges are cut off.
+    pub fn tile_on_screen(
+        &self,
+        screen: &Screen,
+        alignment: Alignment,
+        offset: Offset,
+    ) -> Vec<ImagePlacement> {
+        let (horizontal, vertical) = alignment.on_axes();
+
+        let columns = tile_axis(self.width, screen.width, horizontal, offset.x);
+        let rows = tile_axis(self.height, screen.height, vertical, offset.y);
+
+        let mut placements: Vec<ImagePlacement> = Vec::with_capacity(columns.len() * rows.len());
+
+        for y in &rows {
+            for x in &columns {
+                let tile = ImagePlacement::new(
+                    0,
+                    0,
+                    screen.x_org + x,
+                    screen.y_org + y,
+                    self.width,
+                    self.height,
+                );
 
-        if screen.width > width {
-            out.dest_x = screen.x_org + ((screen.width - width) / 2) as i32;
+                placements.extend(tile.clip_to_screen(screen));
+            }
         }
 
-        if screen.height > height {
-            out.dest_y = screen.y_org + ((screen.height - height) / 2) as i32;
+        placements
+    }
+}
+
+/// Aligns an image on a single axis of a screen. Returns the visible origin
+/// in the image, its position on the screen and its visible length.
+fn align_on_axis(
+    length: u32,
+    screen_origin: i32,
+    screen_length: u32,
+    alignment: AxisAlignment,
+    offset: i32,
+) -> (i32, i32, u32) {
+    let position = aligned_position(length, screen_length, alignment, offset);
+
+    let src = (-position).max(0);
+    let dest = position.max(0);
+    let visible = (length as i64 - src)
+        .min(screen_length as i64 - dest)
+        .max(0);
+
+    (src as i32, screen_origin + dest as i32, visible as u32)
+}
+
+/// Position of an image on a single axis, relative to the screen-origin.
+/// Negative or beyond the screen-length, if the image is cut off.
+fn aligned_position(length: u32, screen_length: u32, alignment: AxisAlignment, offset: i32) -> i64 {
+    // Negative, if the image is larger than the screen
+    let free = screen_length as i64 - length as i64;
+
+    offset as i64
+        + match alignment {
+            AxisAlignment::START => 0,
+            AxisAlignment::CENTER => free / 2,
+            AxisAlignment::END => free,
         }
+}
+
+/// Positions of all tiles on a single axis, relative to the screen-origin,
+/// to cover the screen. The first tile may start before the screen.
+fn tile_axis(length: u32, screen_length: u32, alignment: AxisAlignment, offset: i32) -> Vec<i32> {
+    if length == 0 {
+        return Vec::new();
+    }
+
+    let aligned = aligned_position(length, screen_length, alignment, offset);
+    let length = length as i64;
 
-        return out;
+    // Step back from the aligned tile to the last one, starting before or at 0
+    let mut position = aligned.rem_euclid(length);
+    if position > 0 {
+        position -= length;
     }
+
+    let mut positions: Vec<i32> = Vec::new();
+
+    while position < screen_length as i64 {
+        positions.push(position as i32);
+        position += length;
+    }
+
+    positions
 }
 
 #[cfg(test)]
 mod tests {
+    use super::tile_axis;
     use super::Alignment;
+    use super::AxisAlignment;
+    use super::Crop;
     use super::ImagePlacement;
+    use super::Offset;
     use super::Resolution;
     use super::Scaling;
     use super::Screen;
+    use super::TargetSize;
+    use super::MAX_TARGET_SIZE;
 
     #[test]
     fn when_position_is_center_then_target_resolutions_equals_image_resolution() {
@@ -367,10 +678,422 @@ mod tests {
     #[test]
     fn when_image_5x5_screen_3x3_then_center() {
         let screen = _create_screen0_3x3();
-        let actual = Resolution::new(5, 5).position_on_screen(&screen, Alignment::CENTER);
+        let actual =
+            Resolution::new(5, 5).position_on_screen(&screen, Alignment::CENTER, Offset::default());
         assert_eq!(actual, ImagePlacement::new(1, 1, 0, 0, 3, 3));
     }
 
+    #[test]
+    fn when_image_1x1_screen_3x3_then_align_to_each_anchor() {
+        let expected_destinations = vec![
+            (Alignment::TOP_LEFT, 0, 0),
+            (Alignment::TOP, 1, 0),
+            (Alignment::TOP_RIGHT, 2, 0),
+            (Alignment::LEFT, 0, 1),
+            (Alignment::CENTER, 1, 1),
+            (Alignment::RIGHT, 2, 1),
+            (Alignment::BOTTOM_LEFT, 0, 2),
+            (Alignment::BOTTOM, 1, 2),
+            (Alignment::BOTTOM_RIGHT, 2, 2),
+        ];
+
+        for (alignment, dest_x, dest_y) in expected_destinations {
+            _test_align_on_screen(
+                Resolution::new(1, 1),
+                alignment,
+                Offset::default(),
+                ImagePlacement::new(0, 0, dest_x, dest_y, 1, 1),
+            );
+        }
+    }
+
+    #[test]
+    fn when_image_5x5_cropped_by_screen_3x3_then_show_part_at_each_anchor() {
+        let expected_sources = vec![
+            (Alignment::TOP_LEFT, 0, 0),
+            (Alignment::TOP, 1, 0),
+            (Alignment::TOP_RIGHT, 2, 0),
+            (Alignment::LEFT, 0, 1),
+            (Alignment::CENTER, 1, 1),
+            (Alignment::RIGHT, 2, 1),
+            (Alignment::BOTTOM_LEFT, 0, 2),
+            (Alignment::BOTTOM, 1, 2),
+            (Alignment::BOTTOM_RIGHT, 2, 2),
+        ];
+
+        for (alignment, src_x, src_y) in expected_sources {
+            _test_align_on_screen(
+                Resolution::new(5, 5),
+                alignment,
+                Offset::default(),
+                ImagePlacement::new(src_x, src_y, 0, 0, 3, 3),
+            );
+        }
+    }
+
+    #[test]
+    fn when_image_is_filled_and_aligned_top_left_then_cut_off_right_and_bottom() {
+        let screen = Screen {
+            screen_number: 0,
+            x_org: 1920,
+            y_org: 0,
+            width: 1920,
+            height: 1080,
+        };
+
+        let target = Resolution::new(1000, 1000).fit_to_screen(
+            &Resolution::new(screen.width, screen.height),
+            &Scaling::FILL,
+        );
+        let actual = target.position_on_screen(&screen, Alignment::TOP_LEFT, Offset::default());
+
+        assert_eq!(actual, ImagePlacement::new(0, 0, 1920, 0, 1920, 1080));
+    }
+
+    #[test]
+    fn when_image_1x1_screen_3x3_is_offset_then_move_it() {
+        _test_align_on_screen(
+            Resolution::new(1, 1),
+            Alignment::TOP_LEFT,
+            Offset::new(2, 1),
+            ImagePlacement::new(0, 0, 2, 1, 1, 1),
+        );
+        _test_align_on_screen(
+            Resolution::new(1, 1),
+            Alignment::BOTTOM_RIGHT,
+            Offset::new(-2, -1),
+            ImagePlacement::new(0, 0, 0, 1, 1, 1),
+        );
+    }
+
+    #[test]
+    fn when_image_5x5_screen_3x3_is_offset_then_move_visible_part() {
+        _test_align_on_screen(
+            Resolution::new(5, 5),
+            Alignment::CENTER,
+            Offset::new(1, -1),
+            ImagePlacement::new(0, 2, 0, 0, 3, 3),
+        );
+        _test_align_on_screen(
+            Resolution::new(5, 5),
+            Alignment::TOP_LEFT,
+            Offset::new(1, 0),
+            ImagePlacement::new(0, 0, 1, 0, 2, 3),
+        );
+    }
+
+    #[test]
+    fn when_image_is_offset_beyond_screen_then_nothing_is_visible() {
+        _test_align_on_screen(
+            Resolution::new(1, 1),
+            Alignment::RIGHT,
+            Offset::new(1, 0),
+            ImagePlacement::new(0, 0, 3, 1, 0, 1),
+        );
+        _test_align_on_screen(
+            Resolution::new(1, 1),
+            Alignment::TOP,
+            Offset::new(0, -1),
+            ImagePlacement::new(0, 1, 1, 0, 1, 0),
+        );
+    }
+
+    #[test]
+    fn when_placement_is_within_screen_then_clip_nothing() {
+        let placement = ImagePlacement::new(1, 1, 0, 0, 3, 3);
+        assert_eq!(
+            placement.clip_to_screen(&_create_screen0_3x3()),
+            Some(placement)
+        );
+    }
+
+    #[test]
+    fn when_placement_spans_screens_then_clip_to_slice_of_each() {
+        // Canvas of 1920x1080 left and 1280x1024 right, image filling both
+        let placement = ImagePlacement::new(0, 0, 0, 0, 3200, 1080);
+        let left = _create_screen(0, 0, 1920, 1080);
+        let right = _create_screen(1920, 0, 1280, 1024);
+
+        assert_eq!(
+            placement.clip_to_screen(&left),
+            Some(ImagePlacement::new(0, 0, 0, 0, 1920, 1080))
+        );
+        assert_eq!(
+            placement.clip_to_screen(&right),
+            Some(ImagePlacement::new(1920, 0, 1920, 0, 1280, 1024))
+        );
+    }
+
+    #[test]
+    fn when_placement_is_cropped_and_offset_then_clip_source_accordingly() {
+        let placement = ImagePlacement::new(10, 20, -5, 100, 500, 500);
+        let screen = _create_screen(0, 200, 300, 300);
+
+        assert_eq!(
+            placement.clip_to_screen(&screen),
+            Some(ImagePlacement::new(15, 120, 0, 200, 300, 300))
+        );
+    }
+
+    #[test]
+    fn when_placement_is_beside_screen_then_clip_all() {
+        let placement = ImagePlacement::new(0, 0, 0, 0, 3, 3);
+        let screen = _create_screen(3, 0, 3, 3);
+
+        assert_eq!(placement.clip_to_screen(&screen), None);
+    }
+
+    #[test]
+    fn when_image_1000x1000_screen_1920_1080_position_stretch_then_target_1920_1080() {
+        _test_compute_resolution(
+            Resolution::new(1000, 1000),
+            Resolution::new(1920, 1080),
+            Scaling::STRETCH,
+            Resolution::new(1920, 1080),
+        );
+    }
+
+    #[test]
+    fn when_image_2x1_screen_1280_1024_position_stretch_then_target_1280_1024() {
+        _test_compute_resolution(
+            Resolution::new(2, 1),
+            Resolution::new(1280, 1024),
+            Scaling::STRETCH,
+            Resolution::new(1280, 1024),
+        );
+    }
+
+    #[test]
+    fn when_image_320x200_screen_1920_1080_position_integer_then_target_1600_1000() {
+        _test_compute_resolution(
+            Resolution::new(320, 200),
+            Resolution::new(1920, 1080),
+            Scaling::INTEGER,
+            Resolution::new(1600, 1000),
+        );
+    }
+
+    #[test]
+    fn when_image_1x1_screen_1920_1080_position_integer_then_target_1080_1080() {
+        _test_compute_resolution(
+            Resolution::new(1, 1),
+            Resolution::new(1920, 1080),
+            Scaling::INTEGER,
+            Resolution::new(1080, 1080),
+        );
+    }
+
+    #[test]
+    fn when_image_960x540_screen_1920_1080_position_integer_then_target_1920_1080() {
+        _test_compute_resolution(
+            Resolution::new(960, 540),
+            Resolution::new(1920, 1080),
+            Scaling::INTEGER,
+            Resolution::new(1920, 1080),
+        );
+    }
+
+    #[test]
+    fn when_image_larger_than_screen_position_integer_then_keep_resolution() {
+        _test_compute_resolution(
+            Resolution::new(2000, 500),
+            Resolution::new(1920, 1080),
+            Scaling::INTEGER,
+            Resolution::new(2000, 500),
+        );
+    }
+
+    #[test]
+    fn when_image_is_zoomed_then_ignore_scaling_to_screen() {
+        let actual = Resolution::new(320, 200).fit_to_target(
+            &Resolution::new(1920, 1080),
+            &Scaling::FILL,
+            &Some(TargetSize::ZOOM(3.0)),
+        );
+
+        assert_eq!(actual, Resolution::new(960, 600));
+    }
+
+    #[test]
+    fn when_image_is_zoomed_by_fraction_then_round_to_pixels() {
+        let actual = Resolution::new(3, 1).fit_to_target(
+            &Resolution::new(1920, 1080),
+            &Scaling::NONE,
+            &Some(TargetSize::ZOOM(0.5)),
+        );
+
+        assert_eq!(actual, Resolution::new(2, 1));
+    }
+
+    #[test]
+    fn when_zoomed_image_exceeds_max_target_size_then_limit_it() {
+        let actual = Resolution::new(1000, 500).fit_to_target(
+            &Resolution::new(1920, 1080),
+            &Scaling::NONE,
+            &Some(TargetSize::ZOOM(100.0)),
+        );
+
+        assert_eq!(actual, Resolution::new(16384, 8192));
+    }
+
+    #[test]
+    fn when_image_has_explicit_resolution_then_use_it() {
+        let actual = Resolution::new(320, 200).fit_to_target(
+            &Resolution::new(1920, 1080),
+            &Scaling::MAX,
+            &Some(TargetSize::RESOLUTION(Resolution::new(1280, 720))),
+        );
+
+        assert_eq!(actual, Resolution::new(1280, 720));
+    }
+
+    #[test]
+    fn when_zoomed_image_is_mirror_tiled_then_tile_has_double_zoomed_resolution() {
+        let actual = Resolution::new(16, 8).fit_to_target(
+            &Resolution::new(1920, 1080),
+            &Scaling::MIRROR_TILE,
+            &Some(TargetSize::ZOOM(2.0)),
+        );
+
+        assert_eq!(actual, Resolution::new(64, 32));
+    }
+
+    #[test]
+    fn when_large_image_is_mirror_tiled_then_tile_has_at_most_max_target_size() {
+        let zoomed = Resolution::new(16, 8).fit_to_target(
+            &Resolution::new(1920, 1080),
+            &Scaling::MIRROR_TILE,
+            &Some(TargetSize::ZOOM(MAX_TARGET_SIZE as f32)),
+        );
+
+        assert_eq!(
+            zoomed,
+            Resolution::new(MAX_TARGET_SIZE, MAX_TARGET_SIZE / 2)
+        );
+
+        let sized = Resolution::new(16, 8).fit_to_target(
+            &Resolution::new(1920, 1080),
+            &Scaling::MIRROR_TILE,
+            &Some(TargetSize::RESOLUTION(Resolution::new(
+                MAX_TARGET_SIZE,
+                100,
+            ))),
+        );
+
+        assert_eq!(sized, Resolution::new(MAX_TARGET_SIZE, 200));
+    }
+
+    #[test]
+    fn when_no_size_is_given_then_fit_to_screen() {
+        let actual = Resolution::new(1000, 1000).fit_to_target(
+            &Resolution::new(1920, 1080),
+            &Scaling::MAX,
+            &None,
+        );
+
+        assert_eq!(actual, Resolution::new(1080, 1080));
+    }
+
+    #[test]
+    fn when_image_is_tiled_then_keep_resolution() {
+        _test_compute_resolution(
+            Resolution::new(64, 48),
+            Resolution::new(1920, 1080),
+            Scaling::TILE,
+            Resolution::new(64, 48),
+        );
+    }
+
+    #[test]
+    fn when_image_is_mirror_tiled_then_tile_has_double_resolution() {
+        _test_compute_resolution(
+            Resolution::new(64, 48),
+            Resolution::new(1920, 1080),
+            Scaling::MIRROR_TILE,
+            Resolution::new(128, 96),
+        );
+    }
+
+    #[test]
+    fn when_image_2x2_is_tiled_top_left_on_screen_3x3_then_cut_off_right_and_bottom() {
+        let actual = Resolution::new(2, 2).tile_on_screen(
+            &_create_screen(10, 20, 3, 3),
+            Alignment::TOP_LEFT,
+            Offset::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![
+                ImagePlacement::new(0, 0, 10, 20, 2, 2),
+                ImagePlacement::new(0, 0, 12, 20, 1, 2),
+                ImagePlacement::new(0, 0, 10, 22, 2, 1),
+                ImagePlacement::new(0, 0, 12, 22, 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn when_image_2x2_is_tiled_centered_on_screen_5x1_then_cut_off_both_sides() {
+        let actual = Resolution::new(2, 2).tile_on_screen(
+            &_create_screen(0, 0, 5, 1),
+            Alignment::CENTER,
+            Offset::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![
+                ImagePlacement::new(1, 0, 0, 0, 1, 1),
+                ImagePlacement::new(0, 0, 1, 0, 2, 1),
+                ImagePlacement::new(0, 0, 3, 0, 2, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn when_tiles_are_aligned_then_one_tile_is_at_anchor() {
+        assert_eq!(tile_axis(2, 5, AxisAlignment::START, 0), vec![0, 2, 4]);
+        assert_eq!(tile_axis(2, 5, AxisAlignment::CENTER, 0), vec![-1, 1, 3]);
+        assert_eq!(tile_axis(2, 5, AxisAlignment::END, 0), vec![-1, 1, 3]);
+        assert_eq!(tile_axis(2, 4, AxisAlignment::END, 0), vec![0, 2]);
+    }
+
+    #[test]
+    fn when_tiles_are_offset_then_shift_them_by_less_than_a_tile() {
+        assert_eq!(tile_axis(2, 4, AxisAlignment::START, 1), vec![-1, 1, 3]);
+        assert_eq!(tile_axis(2, 4, AxisAlignment::START, -3), vec![-1, 1, 3]);
+        assert_eq!(tile_axis(2, 4, AxisAlignment::START, 100), vec![0, 2]);
+    }
+
+    #[test]
+    fn when_image_is_larger_than_screen_then_single_tile() {
+        assert_eq!(tile_axis(10, 4, AxisAlignment::CENTER, 0), vec![-3]);
+    }
+
+    #[test]
+    fn when_crop_is_within_image_then_keep_it() {
+        let crop = Crop::new(10, 20, 100, 50);
+        assert_eq!(crop.clip_to_image(&Resolution::new(320, 200)), Some(crop));
+        assert_eq!(crop.resolution(), Resolution::new(100, 50));
+    }
+
+    #[test]
+    fn when_crop_exceeds_image_then_clip_it() {
+        let crop = Crop::new(300, 180, 100, 50);
+        assert_eq!(
+            crop.clip_to_image(&Resolution::new(320, 200)),
+            Some(Crop::new(300, 180, 20, 20))
+        );
+    }
+
+    #[test]
+    fn when_crop_is_beside_image_then_nothing_is_left() {
+        let image = Resolution::new(320, 200);
+        assert_eq!(Crop::new(320, 0, 10, 10).clip_to_image(&image), None);
+        assert_eq!(Crop::new(0, 200, 10, 10).clip_to_image(&image), None);
+    }
+
     fn _test_compute_fill_resolution(image: Resolution, screen: Resolution, expected: Resolution) {
         _test_compute_resolution(image, screen, Scaling::FILL, expected);
     }
@@ -394,6 +1117,16 @@ mod tests {
         assert_eq!(true, actual == expected);
     }
 
+    fn _create_screen(x_org: i32, y_org: i32, width: u32, height: u32) -> Screen {
+        Screen {
+            screen_number: 0,
+            x_org,
+            y_org,
+            width,
+            height,
+        }
+    }
+
     fn _create_screen0_3x3() -> Screen {
         Screen {
             screen_number: 0,
@@ -405,8 +1138,27 @@ mod tests {
     }
 
     fn _test_center_on_screen(width: u32, height: u32, expected: ImagePlacement) {
+        _test_align_on_screen(
+            Resolution::new(width, height),
+            Alignment::CENTER,
+            Offset::default(),
+            expected,
+        );
+    }
+
+    fn _test_align_on_screen(
+        image: Resolution,
+        alignment: Alignment,
+        offset: Offset,
+        expected: ImagePlacement,
+    ) {
         let screen = _create_screen0_3x3();
-        let actual = Resolution::new(width, height).position_on_screen(&screen, Alignment::CENTER);
+        let actual = image.position_on_screen(&screen, alignment, offset);
+
+        if actual != expected {
+            eprintln!("{:?}: actual != expected", alignment);
+        }
+
         assert_eq!(actual, expected);
     }
 }
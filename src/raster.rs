@@ -0,0 +1,346 @@
+//! Transformations of rasters with 4 bytes per pixel, row by row, independent
+//! of the order of color-channels.
+
+use std::sync::OnceLock;
+
+use crate::position::{Crop, ImagePlacement, Resolution};
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Multiplies the color-channels of RGBA-pixels by their alpha. So transparent
+/// pixels don't bleed their color into their neighbors, while scaling.
+pub fn premultiply(rgba: &[u8]) -> Vec<u8> {
+    let mut premultiplied: Vec<u8> = Vec::with_capacity(rgba.len());
+
+    for pixel in rgba.chunks_exact(BYTES_PER_PIXEL) {
+        let alpha = pixel[3] as u32;
+
+        for &channel in &pixel[0..3] {
+            premultiplied.push(((channel as u32 * alpha + 127) / 255) as u8);
+        }
+
+        premultiplied.push(pixel[3]);
+    }
+
+    premultiplied
+}
+
+/// Paints premultiplied RGBA-pixels over an opaque RGBA-backdrop. The backdrop
+/// is a raster of the same size or a single color for all pixels.
+pub fn composite_over(premultiplied: &[u8], backdrop: &[u8]) -> Vec<u8> {
+    let mut composed: Vec<u8> = Vec::with_capacity(premultiplied.len());
+
+    let backdrop = backdrop.chunks_exact(BYTES_PER_PIXEL).cycle();
+
+    for (pixel, background_rgba) in premultiplied.chunks_exact(BYTES_PER_PIXEL).zip(backdrop) {
+        let transparency = 255 - pixel[3] as u32;
+
+        for index in 0..3 {
+            let background = (background_rgba[index] as u32 * transparency + 127) / 255;
+            composed.push((pixel[index] as u32 + background).min(255) as u8);
+        }
+
+        composed.push(255);
+    }
+
+    composed
+}
+
+/// Converts sRGB-pixels to linear light with 16 bits per channel and
+/// multiplies them by alpha. So scaling mixes colors like light does.
+pub fn premultiply_linear(rgba: &[u8]) -> Vec<u16> {
+    let to_linear = srgb_to_linear_table();
+
+    let mut premultiplied: Vec<u16> = Vec::with_capacity(rgba.len());
+
+    for pixel in rgba.chunks_exact(BYTES_PER_PIXEL) {
+        let alpha = pixel[3] as u32 * 257;
+
+        for &channel in &pixel[0..3] {
+            premultiplied
+                .push(((to_linear[channel as usize] as u32 * alpha + 32767) / 65535) as u16);
+        }
+
+        premultiplied.push(alpha as u16);
+    }
+
+    premultiplied
+}
+
+/// Paints premultiplied pixels in linear light over an opaque RGBA-backdrop,
+/// like `composite_over`, and converts them back to sRGB-pixels.
+pub fn composite_linear_over(premultiplied: &[u16], backdrop: &[u8]) -> Vec<u8> {
+    let to_linear = srgb_to_linear_table();
+    let to_srgb = linear_to_srgb_table();
+
+    let mut composed: Vec<u8> = Vec::with_capacity(premultiplied.len());
+
+    let backdrop = backdrop.chunks_exact(BYTES_PER_PIXEL).cycle();
+
+    for (pixel, background_rgba) in premultiplied.chunks_exact(BYTES_PER_PIXEL).zip(backdrop) {
+        let transparency = 65535 - pixel[3] as u32;
+
+        for index in 0..3 {
+            let background =
+                (to_linear[background_rgba[index] as usize] as u32 * transparency + 32767) / 65535;
+            let linear = (pixel[index] as u32 + background).min(65535);
+
+            composed.push(to_srgb[linear as usize]);
+        }
+
+        composed.push(255);
+    }
+
+    composed
+}
+
+/// Cuts the region out of the raster. The region must lie within the raster.
+pub fn crop(raster: &[u8], resolution: &Resolution, region: &Crop) -> Vec<u8> {
+    let row_size = resolution.width as usize * BYTES_PER_PIXEL;
+    let start = region.x as usize * BYTES_PER_PIXEL;
+    let end = start + region.width as usize * BYTES_PER_PIXEL;
+
+    let mut cropped: Vec<u8> =
+        Vec::with_capacity(region.width as usize * region.height as usize * BYTES_PER_PIXEL);
+
+    for row in raster
+        .chunks_exact(row_size)
+        .skip(region.y as usize)
+        .take(region.height as usize)
+    {
+        cropped.extend_from_slice(&row[start..end]);
+    }
+
+    cropped
+}
+
+/// Copies the placed part of the raster onto a canvas. The placement must lie
+/// within both.
+pub fn paint(
+    raster: &[u8],
+    resolution: &Resolution,
+    canvas: &mut [u8],
+    canvas_resolution: &Resolution,
+    placement: &ImagePlacement,
+) {
+    let row_size = resolution.width as usize * BYTES_PER_PIXEL;
+    let canvas_row_size = canvas_resolution.width as usize * BYTES_PER_PIXEL;
+    let length = placement.width as usize * BYTES_PER_PIXEL;
+
+    for y in 0..placement.height as usize {
+        let src =
+            (placement.src_y as usize + y) * row_size + placement.src_x as usize * BYTES_PER_PIXEL;
+        let dest = (placement.dest_y as usize + y) * canvas_row_size
+            + placement.dest_x as usize * BYTES_PER_PIXEL;
+
+        canvas[dest..dest + length].copy_from_slice(&raster[src..src + length]);
+    }
+}
+
+/// Arranges the raster with its mirrored copies as a tile of double width and
+/// height: The original top-left, mirrored horizontally top-right, vertically
+/// bottom-left and both ways bottom-right. Repeating this tile has no seams.
+pub fn mirror_tile(raster: &[u8], resolution: &Resolution) -> Vec<u8> {
+    let width = resolution.width as usize;
+    let height = resolution.height as usize;
+    let row_size = width * BYTES_PER_PIXEL;
+
+    let mut tile: Vec<u8> = Vec::with_capacity(raster.len() * 4);
+
+    for y in (0..height).chain((0..height).rev()) {
+        let row = &raster[y * row_size..(y + 1) * row_size];
+
+        tile.extend_from_slice(row);
+
+        for pixel in row.chunks_exact(BYTES_PER_PIXEL).rev() {
+            tile.extend_from_slice(pixel);
+        }
+    }
+
+    tile
+}
+
+/// Linear light in 16 bits for each 8-bit sRGB-value. Built once.
+fn srgb_to_linear_table() -> &'static [u16] {
+    static TABLE: OnceLock<Vec<u16>> = OnceLock::new();
+
+    TABLE.get_or_init(|| {
+        (0..256)
+            .map(|value| {
+                let srgb = value as f32 / 255.0;
+                let linear = if srgb <= 0.04045 {
+                    srgb / 12.92
+                } else {
+                    ((srgb + 0.055) / 1.055).powf(2.4)
+                };
+
+                (linear * 65535.0).round() as u16
+            })
+            .collect()
+    })
+}
+
+/// 8-bit sRGB-value for each 16-bit value of linear light. Built once.
+fn linear_to_srgb_table() -> &'static [u8] {
+    static TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+
+    TABLE.get_or_init(|| {
+        (0..65536)
+            .map(|value| {
+                let linear = value as f32 / 65535.0;
+                let srgb = if linear <= 0.003_130_8 {
+                    linear * 12.92
+                } else {
+                    1.055 * linear.powf(1.0 / 2.4) - 0.055
+                };
+
+                (srgb * 255.0).round() as u8
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{composite_linear_over, composite_over, crop, mirror_tile, paint};
+    use super::{premultiply, premultiply_linear};
+    use crate::position::{Crop, ImagePlacement, Resolution};
+
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    #[test]
+    fn when_pixel_is_transparent_then_premultiplied_is_zero() {
+        assert_eq!(premultiply(&[200, 100, 50, 0]), vec![0, 0, 0, 0]);
+        assert_eq!(premultiply_linear(&[200, 100, 50, 0]), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn when_pixel_is_opaque_then_premultiplied_is_unchanged() {
+        assert_eq!(premultiply(&[200, 100, 50, 255]), vec![200, 100, 50, 255]);
+    }
+
+    #[test]
+    fn when_premultiplied_pixels_are_composited_then_show_background_by_transparency() {
+        let pixels = premultiply(&[200, 100, 50, 255, 200, 100, 50, 0, 255, 0, 0, 128]);
+
+        assert_eq!(
+            composite_over(&pixels, &WHITE),
+            vec![200, 100, 50, 255, 255, 255, 255, 255, 255, 127, 127, 255]
+        );
+    }
+
+    #[test]
+    fn when_backdrop_is_a_raster_then_show_it_pixel_by_pixel() {
+        let pixels = premultiply(&[200, 100, 50, 0, 200, 100, 50, 0]);
+        let backdrop = [1, 2, 3, 255, 4, 5, 6, 255];
+
+        assert_eq!(composite_over(&pixels, &backdrop), backdrop.to_vec());
+
+        let linear = premultiply_linear(&[200, 100, 50, 0, 200, 100, 50, 0]);
+
+        assert_eq!(composite_linear_over(&linear, &backdrop), backdrop.to_vec());
+    }
+
+    #[test]
+    fn when_opaque_pixels_pass_linear_light_then_they_are_unchanged() {
+        let rgba: Vec<u8> = (0..=255)
+            .flat_map(|value| vec![value, value, value, 255])
+            .collect();
+
+        let actual = composite_linear_over(&premultiply_linear(&rgba), &WHITE);
+
+        assert_eq!(actual, rgba);
+    }
+
+    #[test]
+    fn when_transparent_pixel_is_composited_in_linear_light_then_show_background() {
+        let pixels = premultiply_linear(&[200, 100, 50, 0]);
+
+        assert_eq!(
+            composite_linear_over(&pixels, &[10, 20, 30, 255]),
+            vec![10, 20, 30, 255]
+        );
+    }
+
+    #[test]
+    fn when_raster_is_cropped_then_keep_region_only() {
+        // 3x2 pixels, numbered row by row
+        let raster = vec![
+            1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, //
+            4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6,
+        ];
+
+        let actual = crop(&raster, &Resolution::new(3, 2), &Crop::new(1, 1, 2, 1));
+
+        assert_eq!(actual, vec![5, 5, 5, 5, 6, 6, 6, 6]);
+    }
+
+    #[test]
+    fn when_crop_covers_raster_then_keep_all() {
+        let raster = vec![1, 1, 1, 1, 2, 2, 2, 2];
+
+        let actual = crop(&raster, &Resolution::new(1, 2), &Crop::new(0, 0, 1, 2));
+
+        assert_eq!(actual, raster);
+    }
+
+    #[test]
+    fn when_raster_is_painted_then_copy_placed_part_only() {
+        // 2x2 pixels, numbered row by row
+        let raster = vec![
+            1, 1, 1, 1, 2, 2, 2, 2, //
+            3, 3, 3, 3, 4, 4, 4, 4,
+        ];
+        let mut canvas = vec![0; 3 * 2 * 4];
+
+        paint(
+            &raster,
+            &Resolution::new(2, 2),
+            &mut canvas,
+            &Resolution::new(3, 2),
+            &ImagePlacement::new(1, 0, 2, 1, 1, 1),
+        );
+
+        assert_eq!(
+            canvas,
+            vec![
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, //
+                0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 2, 2,
+            ]
+        );
+    }
+
+    #[test]
+    fn when_raster_is_mirror_tiled_then_mirror_right_and_down() {
+        // 2x1 pixels, 1 left of 2
+        let raster = vec![1, 1, 1, 1, 2, 2, 2, 2];
+
+        let actual = mirror_tile(&raster, &Resolution::new(2, 1));
+
+        assert_eq!(
+            actual,
+            vec![
+                1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, //
+                1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1,
+            ]
+        );
+    }
+
+    #[test]
+    fn when_raster_has_rows_then_mirror_their_order_below() {
+        // 1x2 pixels, 1 above 2
+        let raster = vec![1, 1, 1, 1, 2, 2, 2, 2];
+
+        let actual = mirror_tile(&raster, &Resolution::new(1, 2));
+
+        assert_eq!(
+            actual,
+            vec![
+                1, 1, 1, 1, 1, 1, 1, 1, //
+                2, 2, 2, 2, 2, 2, 2, 2, //
+                2, 2, 2, 2, 2, 2, 2, 2, //
+                1, 1, 1, 1, 1, 1, 1, 1,
+            ]
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//! Resizes rasters with the filters of `resize`, as selected by options for
+//! up- and down-scaling.
+
+use std::sync::Arc;
+
+use crate::options::Options;
+use crate::pixel_art;
+use crate::position::{Resolution, ScalingFilter};
+
+/// Resize given RGBA-raster to target-resolution.
+pub fn resize_raster(
+    raster: Vec<u8>,
+    image_resolution: &Resolution,
+    target_resolution: &Resolution,
+    options: Arc<Options>,
+) -> Vec<u8> {
+    let (raster, image_resolution) =
+        upscale_pixel_art(raster, image_resolution, target_resolution, &options);
+
+    let src_w = image_resolution.width as usize;
+    let src_h = image_resolution.height as usize;
+    let dst_w = target_resolution.width as usize;
+    let dst_h = target_resolution.height as usize;
+
+    let must_resize = src_w != dst_w || src_h != dst_h;
+
+    if !must_resize {
+        return raster;
+    }
+
+    let (resize_type, _) = select_filter(&options, &image_resolution, target_resolution);
+
+    let sample_size = dst_w * dst_h * 4;
+
+    let mut dst: Vec<u8> = Vec::with_capacity(sample_size);
+    dst.resize(sample_size, 0);
+
+    let mut resizer = resize::new(src_w, src_h, dst_w, dst_h, resize::Pixel::RGBA, resize_type);
+
+    resizer.resize(&raster, &mut dst);
+
+    dst
+}
+
+/// Resize given RGBA-raster with 16 bits per channel to target-resolution.
+pub fn resize_linear_raster(
+    raster: Vec<u16>,
+    image_resolution: &Resolution,
+    target_resolution: &Resolution,
+    options: Arc<Options>,
+) -> Vec<u16> {
+    let (raster, image_resolution) =
+        upscale_pixel_art(raster, image_resolution, target_resolution, &options);
+
+    if &image_resolution == target_resolution {
+        return raster;
+    }
+
+    let (resize_type, _) = select_filter(&options, &image_resolution, target_resolution);
+
+    let mut dst: Vec<u16> =
+        vec![0; target_resolution.width as usize * target_resolution.height as usize * 4];
+
+    let mut resizer = resize::new(
+        image_resolution.width as usize,
+        image_resolution.height as usize,
+        target_resolution.width as usize,
+        target_resolution.height as usize,
+        resize::Pixel::RGBA64,
+        resize_type,
+    );
+
+    resizer.resize(&raster, &mut dst);
+
+    dst
+}
+
+/// Enlarges the raster by a pixel-art upscaler, if selected. `resize` only
+/// fits the result to the target-resolution then.
+fn upscale_pixel_art<T: Copy + PartialEq>(
+    raster: Vec<T>,
+    image_resolution: &Resolution,
+    target_resolution: &Resolution,
+    options: &Options,
+) -> (Vec<T>, Resolution) {
+    match scaling_filter(options, image_resolution, target_resolution) {
+        ScalingFilter::SCALE2X => {
+            pixel_art::upscale(raster, image_resolution, target_resolution, 2)
+        }
+        ScalingFilter::SCALE3X => {
+            pixel_art::upscale(raster, image_resolution, target_resolution, 3)
+        }
+        _ => (raster, image_resolution.clone()),
+    }
+}
+
+/// Filter of `resize` to use for given scaling, with its name. Pixel-art
+/// upscalers enlarge until a side covers the target, so the rest is scaled.
+pub fn select_filter(
+    options: &Options,
+    image_resolution: &Resolution,
+    target_resolution: &Resolution,
+) -> (resize::Type, &'static str) {
+    let is_downscaling = is_downscaling(image_resolution, target_resolution);
+
+    match scaling_filter(options, image_resolution, target_resolution) {
+        ScalingFilter::SCALE2X => (resize::Type::Lanczos3, "Scale2x and Lanczos3"),
+        ScalingFilter::SCALE3X => (resize::Type::Lanczos3, "Scale3x and Lanczos3"),
+        ScalingFilter::PIXEL => (resize::Type::Point, "Point"),
+        ScalingFilter::TRIANGLE => (resize::Type::Triangle, "Triangle"),
+        ScalingFilter::CATMULL_ROM => (resize::Type::Catrom, "Catmull-Rom"),
+        ScalingFilter::MITCHELL => (resize::Type::Mitchell, "Mitchell"),
+        ScalingFilter::LANCZOS3 => (resize::Type::Lanczos3, "Lanczos3"),
+        ScalingFilter::AUTO => {
+            if is_downscaling {
+                (resize::Type::Lanczos3, "Lanczos3")
+            } else {
+                (resize::Type::Mitchell, "Mitchell")
+            }
+        }
+    }
+}
+
+/// Filter selected by options. A filter for the direction of scaling
+/// overrides the general one.
+fn scaling_filter<'a>(
+    options: &'a Options,
+    image_resolution: &Resolution,
+    target_resolution: &Resolution,
+) -> &'a ScalingFilter {
+    if is_downscaling(image_resolution, target_resolution) {
+        options.downscaling_filter.as_ref()
+    } else {
+        options.upscaling_filter.as_ref()
+    }
+    .unwrap_or(&options.scaling_filter)
+}
+
+fn is_downscaling(image_resolution: &Resolution, target_resolution: &Resolution) -> bool {
+    let src_pixels = image_resolution.width as u64 * image_resolution.height as u64;
+    let dst_pixels = target_resolution.width as u64 * target_resolution.height as u64;
+
+    src_pixels > dst_pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resize_linear_raster, resize_raster, select_filter};
+    use crate::decoder::{open_frames, Animation};
+    use crate::options::Options;
+    use crate::position::Resolution;
+    use crate::raster::{composite_linear_over, composite_over, premultiply, premultiply_linear};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::sync::Arc;
+
+    const FILTERS: [&str; 5] = ["PIXEL", "TRIANGLE", "CATMULL_ROM", "MITCHELL", "LANCZOS3"];
+
+    #[test]
+    fn when_filters_differ_then_upscaled_samples_differ() {
+        _assert_distinct_hashes(Resolution::new(10, 10));
+    }
+
+    #[test]
+    fn when_filters_differ_then_downscaled_samples_differ() {
+        _assert_distinct_hashes(Resolution::new(3, 3));
+    }
+
+    #[test]
+    fn when_sample_is_scaled_twice_then_hashes_equal() {
+        for filter in FILTERS.iter() {
+            assert_eq!(
+                _scaled_sample_hash(filter, Resolution::new(10, 10)),
+                _scaled_sample_hash(filter, Resolution::new(10, 10))
+            );
+        }
+    }
+
+    #[test]
+    fn when_pixel_filter_doubles_sample_then_repeat_each_pixel() {
+        let (raster, resolution) = _sample();
+        let width = resolution.width as usize;
+
+        let mut expected: Vec<u8> = Vec::new();
+        for row in raster.chunks_exact(width * 4) {
+            let doubled: Vec<u8> = row
+                .chunks_exact(4)
+                .flat_map(|pixel| pixel.iter().chain(pixel.iter()))
+                .copied()
+                .collect();
+
+            expected.extend(&doubled);
+            expected.extend(&doubled);
+        }
+
+        assert_eq!(
+            _scaled_sample_hash("PIXEL", Resolution::new(8, 8)),
+            _hash(&expected)
+        );
+    }
+
+    #[test]
+    fn when_resolution_is_unchanged_then_keep_raster() {
+        let (raster, resolution) = _sample();
+        let options = Arc::new(_options(vec!["--scale-filter", "LANCZOS3"]));
+
+        let actual = resize_raster(raster.clone(), &resolution, &resolution, options);
+
+        assert_eq!(actual, raster);
+    }
+
+    #[test]
+    fn when_black_and_white_are_mixed_then_linear_light_is_brighter() {
+        let black_white = vec![0, 0, 0, 255, 255, 255, 255, 255];
+        let options = Arc::new(_options(vec!["--scale-filter", "TRIANGLE"]));
+        let (from, to) = (Resolution::new(2, 1), Resolution::new(1, 1));
+
+        let srgb = resize_raster(premultiply(&black_white), &from, &to, options.clone());
+        let linear = resize_linear_raster(premultiply_linear(&black_white), &from, &to, options);
+
+        let srgb = composite_over(&srgb, &[0, 0, 0, 255]);
+        let linear = composite_linear_over(&linear, &[0, 0, 0, 255]);
+
+        // Half of the light is about 188 in sRGB, not 128
+        assert!((127..=128).contains(&srgb[0]), "{}", srgb[0]);
+        assert!((187..=188).contains(&linear[0]), "{}", linear[0]);
+    }
+
+    #[test]
+    fn when_transparent_pixels_are_scaled_premultiplied_then_edges_keep_color() {
+        // Red next to transparent black, scaled down over white background
+        let red_transparent = vec![255, 0, 0, 255, 0, 0, 0, 0];
+        let options = Arc::new(_options(vec!["--scale-filter", "TRIANGLE"]));
+        let (from, to) = (Resolution::new(2, 1), Resolution::new(1, 1));
+
+        let scaled = resize_raster(premultiply(&red_transparent), &from, &to, options);
+        let pixel = composite_over(&scaled, &[255, 255, 255, 255]);
+
+        // Red half-transparent over white, no dark fringe
+        assert_eq!(pixel[0], 255);
+        assert!((127..=128).contains(&pixel[1]), "{}", pixel[1]);
+    }
+
+    #[test]
+    fn when_pixel_art_upscaler_doubles_sample_then_match_scale2x() {
+        let (raster, resolution) = _sample();
+        let expected = crate::pixel_art::scale2x(&raster, &resolution);
+
+        assert_eq!(
+            _scaled_sample_hash("SCALE2X", Resolution::new(8, 8)),
+            _hash(&expected)
+        );
+    }
+
+    #[test]
+    fn when_pixel_art_upscaler_exceeds_target_then_scale_rest_down() {
+        let options = _options(vec!["--scale-filter", "SCALE3X"]);
+
+        assert_eq!(_select(&options, 4, 10), "Scale3x and Lanczos3");
+
+        let (raster, resolution) = _sample();
+        let scaled = resize_raster(
+            raster,
+            &resolution,
+            &Resolution::new(10, 10),
+            Arc::new(options),
+        );
+
+        assert_eq!(scaled.len(), 10 * 10 * 4);
+    }
+
+    #[test]
+    fn when_filter_is_auto_then_select_by_direction() {
+        let options = _options(vec![]);
+
+        assert_eq!(_select(&options, 4, 2), "Lanczos3");
+        assert_eq!(_select(&options, 4, 8), "Mitchell");
+    }
+
+    #[test]
+    fn when_filter_is_given_then_use_it_both_ways() {
+        let options = _options(vec!["--scale-filter", "CATMULL_ROM"]);
+
+        assert_eq!(_select(&options, 4, 2), "Catmull-Rom");
+        assert_eq!(_select(&options, 4, 8), "Catmull-Rom");
+    }
+
+    #[test]
+    fn when_filter_per_direction_is_given_then_override_general_one() {
+        let options = _options(vec![
+            "--scale-filter",
+            "TRIANGLE",
+            "--upscale-filter",
+            "PIXEL",
+        ]);
+
+        assert_eq!(_select(&options, 4, 2), "Triangle");
+        assert_eq!(_select(&options, 4, 8), "Point");
+
+        let options = _options(vec!["--downscale-filter", "MITCHELL"]);
+
+        assert_eq!(_select(&options, 4, 2), "Mitchell");
+        assert_eq!(_select(&options, 4, 8), "Mitchell");
+    }
+
+    fn _assert_distinct_hashes(target: Resolution) {
+        let hashes: Vec<u64> = FILTERS
+            .iter()
+            .map(|filter| _scaled_sample_hash(filter, target.clone()))
+            .collect();
+
+        for (i, hash) in hashes.iter().enumerate() {
+            for (j, other) in hashes.iter().enumerate().skip(i + 1) {
+                assert_ne!(hash, other, "{} equals {}", FILTERS[i], FILTERS[j]);
+            }
+        }
+    }
+
+    fn _scaled_sample_hash(filter: &str, target: Resolution) -> u64 {
+        let (raster, resolution) = _sample();
+        let options = Arc::new(_options(vec!["--scale-filter", filter]));
+
+        _hash(&resize_raster(raster, &resolution, &target, options))
+    }
+
+    /// Last frame of a 4x4 sample-GIF, with red and blue pixels.
+    fn _sample() -> (Vec<u8>, Resolution) {
+        let source = open_frames("tests/samples/sample-disposal-keep.gif").unwrap();
+        let mut animation = Animation::from_source(source).unwrap();
+
+        (animation.frames.pop().unwrap().raster, animation.resolution)
+    }
+
+    fn _select(options: &Options, from: u32, to: u32) -> &'static str {
+        select_filter(
+            options,
+            &Resolution::new(from, from),
+            &Resolution::new(to, to),
+        )
+        .1
+    }
+
+    fn _hash(raster: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        raster.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn _options(custom_params: Vec<&str>) -> Options {
+        Options::_from_params([vec!["xgifwallpaper"], custom_params, vec!["a.gif"]].concat())
+    }
+}
@@ -0,0 +1,201 @@
+//! Timelines of wallpapers on screens, each animating with its own frames and
+//! delays, and the schedule across all of them.
+
+use std::time::{Duration, Instant};
+
+/// Current frame of a wallpaper and when its next frame is due.
+#[derive(Debug)]
+pub struct Timeline {
+    frame_index: usize,
+    frame_count: usize,
+    /// Number of times all frames were shown.
+    loops: u32,
+    /// `None`, if there is no next frame, like for a still image.
+    due: Option<Instant>,
+}
+
+impl Timeline {
+    /// Creates a timeline starting with the first frame at `start`.
+    pub fn new(frame_count: usize, start: Instant) -> Timeline {
+        Timeline {
+            frame_index: 0,
+            frame_count,
+            loops: 0,
+            due: Some(start),
+        }
+    }
+
+    /// Index of the frame to draw, when due.
+    pub fn frame_index(&self) -> usize {
+        self.frame_index
+    }
+
+    /// Number of times all frames were shown since the start.
+    pub fn loops(&self) -> u32 {
+        self.loops
+    }
+
+    /// `true`, if the current frame must be drawn at `now`.
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.due.map_or(false, |due| due <= now)
+    }
+
+    /// Point of time the current frame must be drawn, if any.
+    pub fn due(&self) -> Option<Instant> {
+        self.due
+    }
+
+    /// Moves on to the next frame, after the current one was drawn at `now`
+    /// and is shown for `delay`.
+    pub fn advance(&mut self, delay: Duration, now: Instant) {
+        if self.frame_count <= 1 {
+            self.due = None;
+            return;
+        }
+
+        self.frame_index = (self.frame_index + 1) % self.frame_count;
+
+        if self.frame_index == 0 {
+            self.loops += 1;
+        }
+
+        let due = self.due.unwrap_or(now) + delay;
+
+        // Skip the lag, instead of rushing through the next frames
+        self.due = Some(if due < now { now + delay } else { due });
+    }
+}
+
+/// Pause and speed of all timelines, as controlled at runtime.
+#[derive(Debug)]
+pub struct Playback {
+    pub is_paused: bool,
+    /// Factor to divide delays of frames by. Must be positive.
+    pub speed: f32,
+}
+
+impl Playback {
+    /// Creates a running playback in the speed of the files.
+    pub fn new() -> Playback {
+        Playback {
+            is_paused: false,
+            speed: 1.0,
+        }
+    }
+
+    /// Delay of a frame in the current speed.
+    pub fn scale(&self, delay: Duration) -> Duration {
+        delay.div_f64(self.speed as f64)
+    }
+}
+
+/// Earliest point of time a frame of any timeline is due. `None`, if no
+/// timeline has any frame left to draw.
+pub fn next_due<'a, I: Iterator<Item = &'a Timeline>>(timelines: I) -> Option<Instant> {
+    timelines.filter_map(|timeline| timeline.due()).min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_due, Playback, Timeline};
+    use std::time::{Duration, Instant};
+
+    const MS_100: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn when_timeline_starts_then_first_frame_is_due() {
+        let start = Instant::now();
+        let timeline = Timeline::new(3, start);
+
+        assert_eq!(timeline.frame_index(), 0);
+        assert!(timeline.is_due(start));
+    }
+
+    #[test]
+    fn when_frame_was_drawn_then_next_is_due_after_delay() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new(3, start);
+
+        timeline.advance(MS_100, start);
+
+        assert_eq!(timeline.frame_index(), 1);
+        assert!(!timeline.is_due(start));
+        assert_eq!(timeline.due(), Some(start + MS_100));
+    }
+
+    #[test]
+    fn when_last_frame_was_drawn_then_start_over() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new(2, start);
+
+        timeline.advance(MS_100, start);
+        timeline.advance(MS_100, start + MS_100);
+
+        assert_eq!(timeline.frame_index(), 0);
+        assert_eq!(timeline.loops(), 1);
+        assert_eq!(timeline.due(), Some(start + MS_100 * 2));
+    }
+
+    #[test]
+    fn when_drawn_late_then_keep_rhythm_without_drift() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new(3, start);
+
+        timeline.advance(MS_100, start + Duration::from_millis(10));
+
+        assert_eq!(timeline.due(), Some(start + MS_100));
+    }
+
+    #[test]
+    fn when_lagging_more_than_a_frame_then_skip_the_lag() {
+        let start = Instant::now();
+        let now = start + MS_100 * 5;
+        let mut timeline = Timeline::new(3, start);
+
+        timeline.advance(MS_100, now);
+
+        assert_eq!(timeline.due(), Some(now + MS_100));
+    }
+
+    #[test]
+    fn when_timeline_has_single_frame_then_nothing_is_due_after_drawing() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new(1, start);
+
+        timeline.advance(MS_100, start);
+
+        assert_eq!(timeline.due(), None);
+        assert!(!timeline.is_due(start + MS_100 * 100));
+    }
+
+    #[test]
+    fn when_speed_is_set_then_scale_delays_by_it() {
+        let mut playback = Playback::new();
+
+        assert_eq!(playback.scale(MS_100), MS_100);
+
+        playback.speed = 2.0;
+        assert_eq!(playback.scale(MS_100), Duration::from_millis(50));
+
+        playback.speed = 0.5;
+        assert_eq!(playback.scale(MS_100), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn when_timelines_differ_then_next_due_is_earliest() {
+        let start = Instant::now();
+        let mut slow = Timeline::new(2, start);
+        let mut fast = Timeline::new(2, start);
+        let mut still = Timeline::new(1, start);
+
+        slow.advance(MS_100 * 3, start);
+        fast.advance(MS_100, start);
+        still.advance(MS_100, start);
+
+        assert_eq!(
+            next_due(vec![&slow, &fast, &still].into_iter()),
+            Some(start + MS_100)
+        );
+        assert_eq!(next_due(vec![&still].into_iter()), None);
+    }
+}
@@ -16,7 +16,7 @@ pub struct Screens {
 }
 
 /// Information about a single screen.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Screen {
     /// Logical number / id of screen.
     pub screen_number: i32,
@@ -30,6 +30,70 @@ pub struct Screen {
     pub height: u32,
 }
 
+/// Smallest area covering all given screens, including gaps between them. It
+/// has the number of the first screen. `None`, if there are no screens.
+pub fn bounding_box<'a>(mut screens: impl Iterator<Item = &'a Screen>) -> Option<Screen> {
+    let first = screens.next()?.clone();
+
+    let mut right = first.x_org + first.width as i32;
+    let mut bottom = first.y_org + first.height as i32;
+    let mut bounds = first;
+
+    for screen in screens {
+        bounds.x_org = bounds.x_org.min(screen.x_org);
+        bounds.y_org = bounds.y_org.min(screen.y_org);
+        right = right.max(screen.x_org + screen.width as i32);
+        bottom = bottom.max(screen.y_org + screen.height as i32);
+    }
+
+    bounds.width = (right - bounds.x_org) as u32;
+    bounds.height = (bottom - bounds.y_org) as u32;
+
+    Some(bounds)
+}
+
+/// Selects a screen by its index or by its origin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScreenSelector {
+    /// Index of the screen, in order of `Screens::screens`.
+    INDEX(usize),
+    /// Origin of the screen, as `x_org` and `y_org`.
+    POSITION(i32, i32),
+}
+
+impl ScreenSelector {
+    /// Parses an index like `1` or an origin like `+1920+0`, as in
+    /// X11-geometries.
+    pub fn parse(value: &str) -> Option<ScreenSelector> {
+        if value.chars().all(|c| c.is_ascii_digit()) {
+            return value.parse().ok().map(ScreenSelector::INDEX);
+        }
+
+        // Sign of y-offset, after sign and digits of x-offset
+        let split = value
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| *c == '+' || *c == '-')
+            .map(|(i, _)| i)?;
+
+        let (x, y) = value.split_at(split);
+
+        if !(x.starts_with('+') || x.starts_with('-')) {
+            return None;
+        }
+
+        Some(ScreenSelector::POSITION(x.parse().ok()?, y.parse().ok()?))
+    }
+
+    /// `true`, if the screen at `index` is selected.
+    pub fn matches(&self, index: usize, screen: &Screen) -> bool {
+        match *self {
+            ScreenSelector::INDEX(selected) => selected == index,
+            ScreenSelector::POSITION(x, y) => screen.x_org == x && screen.y_org == y,
+        }
+    }
+}
+
 impl Screens {
     /// Queries the running x-server for available screens.
     pub fn query_x_screens() -> Screens {
@@ -93,3 +157,82 @@ impl Screens {
         return has_extension == xlib::True && is_active == xlib::True;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::bounding_box;
+    use super::Screen;
+    use super::ScreenSelector;
+
+    #[test]
+    fn when_screens_differ_in_size_and_have_gaps_then_bounding_box_covers_all() {
+        let screens = vec![
+            _create_screen(0, 1920, 200, 1280, 1024),
+            _create_screen(1, 0, 0, 1920, 1080),
+            _create_screen(2, 3400, -100, 800, 600),
+        ];
+
+        let bounds = bounding_box(screens.iter()).unwrap();
+
+        assert_eq!(bounds.screen_number, 0);
+        assert_eq!((bounds.x_org, bounds.y_org), (0, -100));
+        assert_eq!((bounds.width, bounds.height), (4200, 1324));
+    }
+
+    #[test]
+    fn when_there_are_no_screens_then_there_is_no_bounding_box() {
+        assert!(bounding_box(Vec::<Screen>::new().iter()).is_none());
+    }
+
+    #[test]
+    fn when_selector_is_a_number_then_select_by_index() {
+        assert_eq!(ScreenSelector::parse("1"), Some(ScreenSelector::INDEX(1)));
+    }
+
+    #[test]
+    fn when_selector_is_a_geometry_then_select_by_position() {
+        assert_eq!(
+            ScreenSelector::parse("+1920+0"),
+            Some(ScreenSelector::POSITION(1920, 0))
+        );
+        assert_eq!(
+            ScreenSelector::parse("-1080-200"),
+            Some(ScreenSelector::POSITION(-1080, -200))
+        );
+    }
+
+    #[test]
+    fn when_selector_is_invalid_then_select_nothing() {
+        assert_eq!(ScreenSelector::parse(""), None);
+        assert_eq!(ScreenSelector::parse("left"), None);
+        assert_eq!(ScreenSelector::parse("1920+0"), None);
+        assert_eq!(ScreenSelector::parse("+1920"), None);
+        assert_eq!(ScreenSelector::parse("+1920+"), None);
+    }
+
+    #[test]
+    fn when_screen_matches_index_or_position_then_it_is_selected() {
+        let screen = _create_screen(1, 1920, 0, 1920, 1080);
+
+        assert!(ScreenSelector::INDEX(1).matches(1, &screen));
+        assert!(!ScreenSelector::INDEX(0).matches(1, &screen));
+        assert!(ScreenSelector::POSITION(1920, 0).matches(1, &screen));
+        assert!(!ScreenSelector::POSITION(0, 0).matches(1, &screen));
+    }
+
+    fn _create_screen(
+        screen_number: i32,
+        x_org: i32,
+        y_org: i32,
+        width: u32,
+        height: u32,
+    ) -> Screen {
+        Screen {
+            screen_number,
+            x_org,
+            y_org,
+            width,
+            height,
+        }
+    }
+}
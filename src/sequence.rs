@@ -0,0 +1,316 @@
+//! Reads a directory or a glob of image-files as the frames of a single
+//! animation, in natural order of their file-names.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::decoder::{open_frames, ComposedFrame, FrameSource};
+use crate::position::Resolution;
+
+/// Extensions of files in a directory, taken as frames.
+const FRAME_EXTENSIONS: [&str; 6] = ["bmp", "gif", "jpeg", "jpg", "png", "webp"];
+
+/// Iterator over the frames of an image-sequence. Each file is decoded on
+/// demand and contributes its first frame.
+pub struct SequenceFrames {
+    paths: Vec<PathBuf>,
+    next_index: usize,
+    resolution: Resolution,
+}
+
+impl SequenceFrames {
+    /// Lists the frames of a directory or glob and reads the header of the
+    /// first one. All frames must have its resolution.
+    pub fn open(path: &str) -> Result<SequenceFrames, String> {
+        let paths = frame_paths(path)?;
+
+        let first = paths[0].to_string_lossy();
+        let resolution = open_frames(&first)?.resolution().clone();
+
+        Ok(SequenceFrames {
+            paths,
+            next_index: 0,
+            resolution,
+        })
+    }
+}
+
+impl Iterator for SequenceFrames {
+    type Item = Result<ComposedFrame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let path = self
+            .paths
+            .get(self.next_index)?
+            .to_string_lossy()
+            .into_owned();
+        self.next_index += 1;
+
+        let mut source = match open_frames(&path) {
+            Ok(source) => source,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if source.resolution() != &self.resolution {
+            return Some(Err(format!(
+                "Frame {} has resolution {:?}, expected {:?}",
+                path,
+                source.resolution(),
+                self.resolution
+            )));
+        }
+
+        // Delays of single files are meaningless within a sequence
+        match source.next() {
+            Some(frame) => Some(frame.map(|frame| ComposedFrame {
+                raster: frame.raster,
+                delay_cs: None,
+            })),
+            None => Some(Err(format!("Frame {} contains no image", path))),
+        }
+    }
+}
+
+impl FrameSource for SequenceFrames {
+    fn resolution(&self) -> &Resolution {
+        &self.resolution
+    }
+}
+
+/// `true`, if the path is a directory or a glob, instead of a single file.
+pub fn is_sequence(path: &str) -> bool {
+    is_glob(path) || Path::new(path).is_dir()
+}
+
+/// All files, a path to an image or an image-sequence consists of.
+pub fn input_files(path: &str) -> Result<Vec<PathBuf>, String> {
+    if is_sequence(path) {
+        frame_paths(path)
+    } else {
+        Ok(vec![PathBuf::from(path)])
+    }
+}
+
+/// Files of a directory with an image-extension or files matching a glob, in
+/// natural order. Wildcards are supported in the file-name only.
+fn frame_paths(path: &str) -> Result<Vec<PathBuf>, String> {
+    let (dir, pattern) = if is_glob(path) {
+        let path = Path::new(path);
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let pattern = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+
+        (dir, pattern)
+    } else {
+        (PathBuf::from(path), None)
+    };
+
+    let entries =
+        fs::read_dir(&dir).map_err(|e| format!("Unable to read directory {}: {}", path, e))?;
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| match &pattern {
+            Some(pattern) => matches_wildcard(pattern, name),
+            None => has_frame_extension(name),
+        })
+        .collect();
+
+    if names.is_empty() {
+        return Err(format!("No frames found in {}", path));
+    }
+
+    names.sort_by(|a, b| natural_cmp(a, b));
+
+    Ok(names.into_iter().map(|name| dir.join(name)).collect())
+}
+
+/// `true`, if the path contains wildcards.
+pub fn is_glob(path: &str) -> bool {
+    path.contains('*') || path.contains('?')
+}
+
+fn has_frame_extension(name: &str) -> bool {
+    match Path::new(name).extension() {
+        Some(extension) => {
+            let extension = extension.to_string_lossy().to_lowercase();
+            FRAME_EXTENSIONS.contains(&extension.as_str())
+        }
+        None => false,
+    }
+}
+
+/// Matches a name against a pattern with wildcards `*` for any number of
+/// characters and `?` for a single character.
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the position in the name it matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p + 1, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            // Let the last `*` match one more character
+            p = star_p;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Compares names, treating runs of digits as numbers. So `frame-2` comes
+/// before `frame-10`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+
+                // Compare by length first, numbers may exceed any integer
+                let ordering = x
+                    .trim_start_matches('0')
+                    .len()
+                    .cmp(&y.trim_start_matches('0').len())
+                    .then_with(|| x.trim_start_matches('0').cmp(y.trim_start_matches('0')));
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number<I: Iterator<Item = char>>(chars: &mut std::iter::Peekable<I>) -> String {
+    let mut number = String::new();
+
+    while let Some(c) = chars.peek().copied().filter(|c| c.is_ascii_digit()) {
+        number.push(c);
+        chars.next();
+    }
+
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame_paths, is_sequence, matches_wildcard, natural_cmp, SequenceFrames};
+    use crate::decoder::FrameSource;
+    use crate::position::Resolution;
+    use std::cmp::Ordering;
+    use std::path::PathBuf;
+
+    #[test]
+    fn when_path_is_directory_or_glob_then_it_is_a_sequence() {
+        assert!(is_sequence("tests/samples/sequence"));
+        assert!(is_sequence("tests/samples/sequence/*.png"));
+        assert!(is_sequence("frame-?.png"));
+        assert!(!is_sequence("tests/samples/sample-still.png"));
+    }
+
+    #[test]
+    fn when_directory_is_listed_then_frames_are_in_natural_order() {
+        assert_eq!(
+            frame_paths("tests/samples/sequence").unwrap(),
+            vec![
+                PathBuf::from("tests/samples/sequence/frame-1.png"),
+                PathBuf::from("tests/samples/sequence/frame-2.png"),
+                PathBuf::from("tests/samples/sequence/frame-10.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn when_glob_is_listed_then_only_matching_files_are_frames() {
+        assert_eq!(
+            frame_paths("tests/samples/sequence/frame-?.png").unwrap(),
+            vec![
+                PathBuf::from("tests/samples/sequence/frame-1.png"),
+                PathBuf::from("tests/samples/sequence/frame-2.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn when_nothing_matches_then_fail() {
+        assert!(frame_paths("tests/samples/sequence/*.tiff").is_err());
+        assert!(frame_paths("tests/samples/does-not-exist").is_err());
+    }
+
+    #[test]
+    fn when_sequence_is_read_then_each_file_is_a_frame() {
+        let frames = SequenceFrames::open("tests/samples/sequence").unwrap();
+        assert_eq!(frames.resolution(), &Resolution::new(4, 4));
+
+        let frames: Vec<_> = frames.map(|frame| frame.unwrap()).collect();
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(&frames[0].raster[0..4], &[255, 0, 0, 255]);
+        assert_eq!(&frames[1].raster[0..4], &[0, 255, 0, 255]);
+        assert_eq!(&frames[2].raster[0..4], &[0, 0, 255, 255]);
+        assert!(frames.iter().all(|frame| frame.delay_cs.is_none()));
+    }
+
+    #[test]
+    fn when_frame_has_other_resolution_then_fail_on_it() {
+        let mut frames = SequenceFrames::open("tests/samples/sample-?x?.gif").unwrap();
+
+        // sample-1x1.gif, sample-1x2.gif, sample-2x1.gif
+        assert!(frames.next().unwrap().is_ok());
+        assert!(frames.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn when_pattern_has_wildcards_then_match() {
+        assert!(matches_wildcard("*.png", "frame-1.png"));
+        assert!(matches_wildcard("frame-?.png", "frame-1.png"));
+        assert!(matches_wildcard("f*-*.png", "frame-10.png"));
+        assert!(matches_wildcard("*", ""));
+        assert!(!matches_wildcard("frame-?.png", "frame-10.png"));
+        assert!(!matches_wildcard("*.png", "frame-1.gif"));
+        assert!(!matches_wildcard("*.png", "frame.png.bak"));
+    }
+
+    #[test]
+    fn when_names_contain_numbers_then_compare_them_as_numbers() {
+        assert_eq!(natural_cmp("frame-2", "frame-10"), Ordering::Less);
+        assert_eq!(natural_cmp("frame-10", "frame-9"), Ordering::Greater);
+        assert_eq!(natural_cmp("frame-007", "frame-7"), Ordering::Equal);
+        assert_eq!(natural_cmp("a-1", "b-0"), Ordering::Less);
+        assert_eq!(natural_cmp("frame", "frame-1"), Ordering::Less);
+    }
+}
@@ -0,0 +1,287 @@
+//! Renders frames ahead in a background-thread while animating, to keep only
+//! a bounded number of rendered frames in memory.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use x11::xlib::{Display, XImage};
+use x11::xshm::XShmSegmentInfo;
+
+use crate::decoder::Animation;
+use crate::options::Options;
+use crate::position::Resolution;
+use crate::shm::destroy_xshm_sgmnt_inf;
+use crate::upload_raster;
+use crate::xcontext::XContext;
+use crate::{create_shared_image, get_frame_duration, render_raster, PixelFormat};
+
+/// Frames of a single resolution, rendered ahead by a background-thread as
+/// plain rasters. Rendering stops, once dropped.
+pub struct StreamedFrames {
+    resolution: Resolution,
+    delays: Vec<Duration>,
+    /// Rendered frames with their index, in order.
+    receiver: Receiver<(usize, Vec<u8>)>,
+    worker: JoinHandle<()>,
+}
+
+impl StreamedFrames {
+    /// Starts rendering frames over the backdrop in a loop. At most `capacity`
+    /// rendered frames are queued, until they are due.
+    pub fn start(
+        animation: Arc<Animation>,
+        resolution: &Resolution,
+        capacity: usize,
+        backdrop: Vec<u8>,
+        format: PixelFormat,
+        options: Arc<Options>,
+        running: Arc<AtomicBool>,
+    ) -> StreamedFrames {
+        logln!(
+            options,
+            "Stream frames for {:?}, rendering {} frames ahead",
+            resolution,
+            capacity
+        );
+
+        let delays = animation
+            .frames
+            .iter()
+            .map(|frame| get_frame_duration(frame.delay_cs, options.clone()))
+            .collect();
+
+        let (sender, receiver) = sync_channel(capacity);
+        let target_resolution = resolution.clone();
+
+        let worker = thread::spawn(move || {
+            let mut index = 0;
+
+            while running.load(Ordering::SeqCst) {
+                let raster = render_raster(
+                    &animation.frames[index],
+                    &animation.resolution,
+                    &target_resolution,
+                    &backdrop,
+                    &format,
+                    options.clone(),
+                );
+
+                // Fails only, if the stream was stopped
+                if sender.send((index, raster)).is_err() {
+                    break;
+                }
+
+                index = (index + 1) % animation.frames.len();
+            }
+        });
+
+        StreamedFrames {
+            resolution: resolution.clone(),
+            delays,
+            receiver,
+            worker,
+        }
+    }
+}
+
+/// Frames streamed by a background-thread, uploaded into a single shared
+/// memory segment when due.
+pub struct FrameStream {
+    delays: Vec<Duration>,
+    /// Index of the frame currently in shared memory.
+    current: Option<usize>,
+    ximage: Box<XImage>,
+    xshminfo: Box<XShmSegmentInfo>, // Must exist as long as ximage
+    /// Rendered frames with their index, in order.
+    receiver: Option<Receiver<(usize, Vec<u8>)>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl FrameStream {
+    /// Creates the shared memory segment to upload the streamed frames into.
+    pub fn new(xcontext: &Box<XContext>, frames: StreamedFrames) -> FrameStream {
+        let (ximage, xshminfo) = create_shared_image(xcontext, &frames.resolution);
+
+        FrameStream {
+            delays: frames.delays,
+            current: None,
+            ximage,
+            xshminfo,
+            receiver: Some(frames.receiver),
+            worker: Some(frames.worker),
+        }
+    }
+
+    /// Number of frames in the animation.
+    pub fn len(&self) -> usize {
+        self.delays.len()
+    }
+
+    /// Uploads the frame at `index`, if not already done. Returns its image
+    /// and delay. If the frame is not rendered yet, the newest one received
+    /// is shown instead, only the very first frame is waited for.
+    pub fn frame(&mut self, index: usize) -> (&mut XImage, Duration) {
+        if self.current != Some(index) {
+            if let Some(receiver) = &self.receiver {
+                if let Some((received, raster)) = newest_frame(receiver, self.current, index) {
+                    upload_raster(&self.xshminfo, &raster);
+                    self.current = Some(received);
+                }
+            }
+        }
+
+        (&mut *self.ximage, self.delays[index])
+    }
+
+    /// Stops the background-thread and frees the shared memory.
+    pub fn stop(&mut self, display: *mut Display) {
+        // Dropping the receiver releases a worker blocked on a full queue
+        self.receiver.take();
+
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+
+        unsafe { x11::xshm::XShmDetach(display, self.xshminfo.as_mut() as *mut _) };
+        destroy_xshm_sgmnt_inf(&mut self.xshminfo);
+    }
+}
+
+/// Newest frame received up to the one at `index`, with its index. Frames
+/// received before it are skipped. `None`, if none was received since the
+/// `current` one. Only the very first frame is waited for.
+fn newest_frame(
+    receiver: &Receiver<(usize, Vec<u8>)>,
+    current: Option<usize>,
+    index: usize,
+) -> Option<(usize, Vec<u8>)> {
+    let mut newest = match current {
+        None => receiver.recv().ok(),
+        Some(_) => None,
+    };
+
+    while newest.as_ref().map(|(received, _)| *received) != Some(index) {
+        match receiver.try_recv() {
+            Ok(received) => newest = Some(received),
+            Err(_) => break,
+        }
+    }
+
+    newest
+}
+
+/// Number of frames to render ahead per resolution, if pre-rendering all
+/// frames would exceed the memory-budget in MiB. `None` if there is no budget
+/// or all frames fit into it. The decoded frames of `composed_size` in bytes
+/// are kept while rendering and count against the budget.
+pub fn capacity_for_budget(
+    frame_count: usize,
+    composed_size: u64,
+    resolutions: &[Resolution],
+    memory_budget: Option<u64>,
+) -> Option<usize> {
+    let budget = memory_budget? * 1024 * 1024;
+
+    let bytes_per_frame: u64 = resolutions
+        .iter()
+        .map(|r| r.width as u64 * r.height as u64 * 4)
+        .sum();
+
+    if composed_size + bytes_per_frame * frame_count as u64 <= budget {
+        return None;
+    }
+
+    // One frame per resolution is always held in shared memory
+    let capacity =
+        (budget.saturating_sub(composed_size) / bytes_per_frame.max(1)).saturating_sub(1);
+
+    Some((capacity as usize).max(1))
+}
+
+/// Size in bytes of decoded frames of given resolution.
+pub fn composed_size(frame_count: usize, resolution: &Resolution) -> u64 {
+    frame_count as u64 * resolution.width as u64 * resolution.height as u64 * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{capacity_for_budget, composed_size, newest_frame};
+    use crate::position::Resolution;
+    use std::sync::mpsc::sync_channel;
+
+    const MIB: u32 = 1024 * 1024;
+
+    #[test]
+    fn when_frames_are_rendered_slower_than_played_then_show_newest_one() {
+        let (sender, receiver) = sync_channel(4);
+
+        sender.send((0, vec![0])).unwrap();
+        assert_eq!(newest_frame(&receiver, None, 0), Some((0, vec![0])));
+
+        // Frame 3 is due, but only frame 1 is rendered yet
+        sender.send((1, vec![1])).unwrap();
+        assert_eq!(newest_frame(&receiver, Some(0), 3), Some((1, vec![1])));
+        assert_eq!(newest_frame(&receiver, Some(1), 4), None);
+
+        sender.send((2, vec![2])).unwrap();
+        sender.send((3, vec![3])).unwrap();
+        assert_eq!(newest_frame(&receiver, Some(1), 5), Some((3, vec![3])));
+    }
+
+    #[test]
+    fn when_frames_are_rendered_ahead_then_show_due_one() {
+        let (sender, receiver) = sync_channel(4);
+
+        for index in 0..3 {
+            sender.send((index, vec![index as u8])).unwrap();
+        }
+
+        assert_eq!(newest_frame(&receiver, None, 0), Some((0, vec![0])));
+        assert_eq!(newest_frame(&receiver, Some(0), 1), Some((1, vec![1])));
+    }
+
+    #[test]
+    fn when_no_budget_is_given_then_prerender() {
+        let resolutions = vec![Resolution::new(1920, 1080)];
+        assert_eq!(capacity_for_budget(1000, 0, &resolutions, None), None);
+    }
+
+    #[test]
+    fn when_all_frames_fit_into_budget_then_prerender() {
+        // 10 frames with 1 MiB each
+        let resolutions = vec![Resolution::new(MIB / 4, 1)];
+        assert_eq!(capacity_for_budget(10, 0, &resolutions, Some(10)), None);
+    }
+
+    #[test]
+    fn when_frames_exceed_budget_then_render_ahead_within_budget() {
+        // 100 frames with 2 MiB each, for two resolutions
+        let resolutions = vec![Resolution::new(MIB / 4, 1), Resolution::new(MIB / 4, 1)];
+        assert_eq!(capacity_for_budget(100, 0, &resolutions, Some(20)), Some(9));
+    }
+
+    #[test]
+    fn when_budget_is_smaller_than_a_frame_then_render_ahead_one_frame() {
+        let resolutions = vec![Resolution::new(MIB, 1)];
+        assert_eq!(capacity_for_budget(100, 0, &resolutions, Some(1)), Some(1));
+    }
+
+    #[test]
+    fn when_decoded_frames_take_budget_then_render_ahead_within_rest() {
+        // 10 decoded frames with 1 MiB each, 100 frames with 1 MiB each
+        let composed = composed_size(10, &Resolution::new(MIB / 4, 1));
+        let resolutions = vec![Resolution::new(MIB / 4, 1)];
+
+        assert_eq!(
+            capacity_for_budget(100, composed, &resolutions, Some(20)),
+            Some(9)
+        );
+        assert_eq!(
+            capacity_for_budget(10, composed, &resolutions, Some(20)),
+            None
+        );
+    }
+}
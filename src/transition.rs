@@ -0,0 +1,188 @@
+//! Transitions from the previous content of a screen to a wallpaper, blended
+//! raster by raster, independent of the order of color-channels.
+
+use crate::position::Resolution;
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Way to blend the outgoing content of a screen into the incoming one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transition {
+    /// Fades the outgoing content out, while the incoming fades in.
+    CROSSFADE,
+    /// Uncovers the incoming content from left to right.
+    WIPE,
+    /// Slides the incoming content in from the right, pushing the outgoing
+    /// one out to the left.
+    SLIDE,
+}
+
+/// Blends both rasters of given resolution into `blended`, as shown at
+/// `progress` between 0 and 1 of the transition. Its speed eases in and out.
+///
+/// `blended` must hold the outgoing raster or a blend of an earlier progress
+/// of the same transition. Parts, which still show the outgoing raster, are
+/// not written again.
+pub fn blend(
+    transition: Transition,
+    outgoing: &[u8],
+    incoming: &[u8],
+    resolution: &Resolution,
+    progress: f32,
+    blended: &mut [u8],
+) {
+    let progress = ease(progress);
+
+    let row_size = resolution.width as usize * BYTES_PER_PIXEL;
+    let rows = outgoing
+        .chunks_exact(row_size)
+        .zip(incoming.chunks_exact(row_size))
+        .zip(blended.chunks_exact_mut(row_size));
+
+    match transition {
+        Transition::CROSSFADE => {
+            let weight = (progress * 256.0).round() as u32;
+
+            for ((outgoing, incoming), blended) in rows {
+                for ((&from, &to), target) in outgoing.iter().zip(incoming).zip(blended) {
+                    *target = ((from as u32 * (256 - weight) + to as u32 * weight) / 256) as u8;
+                }
+            }
+        }
+        Transition::WIPE => {
+            let split = columns(progress, resolution) * BYTES_PER_PIXEL;
+
+            for ((_, incoming), blended) in rows {
+                blended[..split].copy_from_slice(&incoming[..split]);
+            }
+        }
+        Transition::SLIDE => {
+            let shift = columns(progress, resolution) * BYTES_PER_PIXEL;
+
+            for ((outgoing, incoming), blended) in rows {
+                let kept = row_size - shift;
+
+                blended[..kept].copy_from_slice(&outgoing[shift..]);
+                blended[kept..].copy_from_slice(&incoming[..shift]);
+            }
+        }
+    }
+}
+
+/// Columns of the incoming content shown at `progress`.
+fn columns(progress: f32, resolution: &Resolution) -> usize {
+    ((progress * resolution.width as f32).round() as usize).min(resolution.width as usize)
+}
+
+/// Smoothstep of the progress, clamped to 0 and 1.
+fn ease(progress: f32) -> f32 {
+    let progress = progress.max(0.0).min(1.0);
+
+    progress * progress * (3.0 - 2.0 * progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blend, Transition};
+    use crate::position::Resolution;
+
+    // 4x1 pixels each
+    const OUTGOING: [u8; 16] = [0; 16];
+    const INCOMING: [u8; 16] = [
+        1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, //
+    ];
+
+    #[test]
+    fn when_wiping_then_leave_outgoing_part_untouched() {
+        let mut blended = vec![9; 16];
+
+        blend(
+            Transition::WIPE,
+            &OUTGOING,
+            &INCOMING,
+            &Resolution::new(4, 1),
+            0.5,
+            &mut blended,
+        );
+
+        assert_eq!(
+            blended,
+            vec![1, 1, 1, 1, 2, 2, 2, 2, 9, 9, 9, 9, 9, 9, 9, 9]
+        );
+    }
+
+    #[test]
+    fn when_transition_starts_then_show_outgoing_only() {
+        for transition in &[Transition::CROSSFADE, Transition::WIPE, Transition::SLIDE] {
+            assert_eq!(_blend(*transition, 0.0), OUTGOING.to_vec());
+        }
+    }
+
+    #[test]
+    fn when_transition_ends_then_show_incoming_only() {
+        for transition in &[Transition::CROSSFADE, Transition::WIPE, Transition::SLIDE] {
+            assert_eq!(_blend(*transition, 1.0), INCOMING.to_vec());
+        }
+    }
+
+    #[test]
+    fn when_crossfading_halfway_then_mix_both_evenly() {
+        let outgoing = [200; 4];
+        let incoming = [100; 4];
+        let mut blended = [0; 4];
+
+        blend(
+            Transition::CROSSFADE,
+            &outgoing,
+            &incoming,
+            &Resolution::new(1, 1),
+            0.5,
+            &mut blended,
+        );
+
+        assert_eq!(blended, [150; 4]);
+    }
+
+    #[test]
+    fn when_wiping_halfway_then_left_half_is_incoming() {
+        assert_eq!(
+            _blend(Transition::WIPE, 0.5),
+            vec![1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn when_sliding_halfway_then_incoming_enters_from_right() {
+        let outgoing: Vec<u8> = vec![5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 8];
+        let mut blended = vec![0; 16];
+
+        blend(
+            Transition::SLIDE,
+            &outgoing,
+            &INCOMING,
+            &Resolution::new(4, 1),
+            0.5,
+            &mut blended,
+        );
+
+        assert_eq!(
+            blended,
+            vec![7, 7, 7, 7, 8, 8, 8, 8, 1, 1, 1, 1, 2, 2, 2, 2]
+        );
+    }
+
+    fn _blend(transition: Transition, progress: f32) -> Vec<u8> {
+        let mut blended = OUTGOING.to_vec();
+
+        blend(
+            transition,
+            &OUTGOING,
+            &INCOMING,
+            &Resolution::new(4, 1),
+            progress,
+            &mut blended,
+        );
+
+        blended
+    }
+}
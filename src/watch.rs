@@ -0,0 +1,320 @@
+//! Watches shown files for changes with inotify, to reload them once written.
+//!
+//! Directories are watched instead of files, so files replaced by editors or
+//! exporters via rename are still noticed. Changes are reported in batches,
+//! once no more follow for a moment.
+
+use std::collections::HashMap;
+use std::ffi::{CString, OsStr, OsString};
+use std::fs;
+use std::os::raw::c_int;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, Thread};
+
+use crate::options::Options;
+use crate::sequence;
+
+/// Time without further changes, after which a file counts as written.
+const QUIET_MS: c_int = 300;
+
+/// Size of the fixed part of an inotify-event, before its name.
+const EVENT_HEADER_SIZE: usize = 16;
+
+const WATCH_MASK: u32 = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_DELETE;
+
+/// Watched directories by their watch-descriptor.
+type Directories = Arc<Mutex<HashMap<c_int, PathBuf>>>;
+
+/// Reports changed files in watched directories from a background-thread.
+pub struct FileWatcher {
+    /// Batches of changed files, as paths within canonical directories.
+    pub changes: Receiver<Vec<PathBuf>>,
+    /// `None`, if files are not watched.
+    fd: Option<c_int>,
+    directories: Directories,
+}
+
+impl FileWatcher {
+    /// Starts watching, if enabled by options. The thread handling the
+    /// changes is woken up on each batch. Without inotify, no changes are
+    /// ever received.
+    pub fn start(options: Arc<Options>, handler: Thread) -> FileWatcher {
+        let directories: Directories = Arc::new(Mutex::new(HashMap::new()));
+        let (sender, changes) = channel();
+
+        let fd = if options.watch {
+            match FileWatcher::start_thread(sender, directories.clone(), handler) {
+                Ok(fd) => Some(fd),
+                Err(e) => {
+                    eprintln!("Files are not watched: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        FileWatcher {
+            changes,
+            fd,
+            directories,
+        }
+    }
+
+    fn start_thread(
+        sender: Sender<Vec<PathBuf>>,
+        directories: Directories,
+        handler: Thread,
+    ) -> Result<c_int, String> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+
+        thread::spawn(move || {
+            while let Some(changed) = read_changes(fd, &directories) {
+                if sender.send(changed).is_err() {
+                    break;
+                }
+
+                handler.unpark();
+            }
+        });
+
+        Ok(fd)
+    }
+
+    /// Watches the directory of the file, image-sequence or playlist at
+    /// `path`. Watching it again has no effect.
+    pub fn watch(&self, path: &str) {
+        let fd = match self.fd {
+            Some(fd) => fd,
+            None => return,
+        };
+
+        let (directory, _) = watch_target(path);
+        let c_directory = CString::new(directory.as_os_str().as_bytes()).unwrap();
+
+        let wd = unsafe { libc::inotify_add_watch(fd, c_directory.as_ptr(), WATCH_MASK) };
+
+        if wd < 0 {
+            eprintln!(
+                "Unable to watch {}: {}",
+                directory.display(),
+                std::io::Error::last_os_error()
+            );
+            return;
+        }
+
+        self.directories.lock().unwrap().insert(wd, directory);
+    }
+}
+
+/// `true`, if the changed file belongs to the file or image-sequence at
+/// `path`. Of an image-sequence, any file in its directory counts.
+pub fn is_affected(path: &str, changed: &Path) -> bool {
+    let (directory, name) = watch_target(path);
+
+    changed.parent() == Some(directory.as_path())
+        && name.map_or(true, |name| changed.file_name() == Some(name.as_os_str()))
+}
+
+/// Canonical directory to watch for the path and the name of its file. The
+/// name is `None` for image-sequences.
+fn watch_target(path: &str) -> (PathBuf, Option<OsString>) {
+    let as_path = Path::new(path);
+
+    let (directory, name) = if as_path.is_dir() {
+        (as_path, None)
+    } else {
+        let directory = match as_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let name = as_path.file_name().map(OsStr::to_os_string);
+
+        // Of a glob, any file of its directory counts
+        if sequence::is_sequence(path) {
+            (directory, None)
+        } else {
+            (directory, name)
+        }
+    };
+
+    let directory = fs::canonicalize(directory).unwrap_or_else(|_| directory.to_path_buf());
+
+    (directory, name)
+}
+
+/// Blocks until files change, then collects further changes until quiet.
+/// `None`, if inotify fails.
+fn read_changes(fd: c_int, directories: &Directories) -> Option<Vec<PathBuf>> {
+    let mut changed: Vec<PathBuf> = Vec::new();
+    let mut buffer = [0u8; 4096];
+
+    while changed.is_empty() || poll(fd, QUIET_MS)? {
+        let length = unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut _, buffer.len()) };
+
+        if length <= 0 {
+            return None;
+        }
+
+        let directories = directories.lock().unwrap();
+        changed.extend(parse_events(&buffer[..length as usize], &directories));
+    }
+
+    changed.sort();
+    changed.dedup();
+
+    Some(changed)
+}
+
+/// `true`, if events can be read within `timeout` in milliseconds.
+fn poll(fd: c_int, timeout: c_int) -> Option<bool> {
+    let mut poll_fd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    match unsafe { libc::poll(&mut poll_fd, 1, timeout) } {
+        -1 => None,
+        ready => Some(ready > 0),
+    }
+}
+
+/// Paths of the files named by inotify-events, within their watched
+/// directories. Events of directories no longer known are skipped.
+fn parse_events(buffer: &[u8], directories: &HashMap<c_int, PathBuf>) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut offset = 0;
+
+    while offset + EVENT_HEADER_SIZE <= buffer.len() {
+        let field = |index: usize| {
+            let start = offset + index * 4;
+            u32::from_ne_bytes([
+                buffer[start],
+                buffer[start + 1],
+                buffer[start + 2],
+                buffer[start + 3],
+            ])
+        };
+
+        let wd = field(0) as c_int;
+        let name_length = field(3) as usize;
+
+        let name_start = offset + EVENT_HEADER_SIZE;
+        let name_end = (name_start + name_length).min(buffer.len());
+
+        // Names are padded with NUL-bytes
+        let name: Vec<u8> = buffer[name_start..name_end]
+            .iter()
+            .copied()
+            .take_while(|byte| *byte != 0)
+            .collect();
+
+        if let Some(directory) = directories.get(&wd) {
+            if !name.is_empty() {
+                paths.push(directory.join(OsString::from_vec(name)));
+            }
+        }
+
+        offset = name_start + name_length;
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_affected, parse_events, FileWatcher};
+    use crate::options::Options;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn when_events_are_parsed_then_join_names_with_directories() {
+        let mut buffer: Vec<u8> = Vec::new();
+        _push_event(&mut buffer, 1, "a.gif", 16);
+        _push_event(&mut buffer, 2, "b.gif", 8);
+        _push_event(&mut buffer, 1, "", 0);
+
+        let mut directories = HashMap::new();
+        directories.insert(1, PathBuf::from("/one"));
+
+        assert_eq!(
+            parse_events(&buffer, &directories),
+            vec![PathBuf::from("/one/a.gif")],
+            "Unknown directories and events without names are skipped"
+        );
+    }
+
+    #[test]
+    fn when_file_changes_then_only_it_is_affected() {
+        let directory = _temp_dir("affected");
+        let path = directory.join("a.gif");
+        fs::write(&path, b"GIF89a").unwrap();
+
+        let path = path.to_string_lossy();
+
+        assert!(is_affected(&path, &directory.join("a.gif")));
+        assert!(!is_affected(&path, &directory.join("b.gif")));
+        assert!(is_affected(
+            &directory.to_string_lossy(),
+            &directory.join("b.gif")
+        ));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn when_watched_file_is_written_then_report_it() {
+        let directory = _temp_dir("watched");
+        let path = directory.join("a.gif");
+
+        let options = Arc::new(Options::_from_params(vec![
+            "xgifwallpaper",
+            "--watch",
+            "a.gif",
+        ]));
+        let watcher = FileWatcher::start(options, thread::current());
+        watcher.watch(&path.to_string_lossy());
+
+        fs::write(&path, b"GIF89a").unwrap();
+
+        let changed = watcher
+            .changes
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(changed, vec![path]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    fn _push_event(buffer: &mut Vec<u8>, wd: i32, name: &str, padded_length: usize) {
+        buffer.extend_from_slice(&wd.to_ne_bytes());
+        buffer.extend_from_slice(&libc::IN_CLOSE_WRITE.to_ne_bytes());
+        buffer.extend_from_slice(&0u32.to_ne_bytes());
+        buffer.extend_from_slice(&(padded_length as u32).to_ne_bytes());
+
+        let mut padded = name.as_bytes().to_vec();
+        padded.resize(padded_length, 0);
+        buffer.extend_from_slice(&padded);
+    }
+
+    fn _temp_dir(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("xgifwallpaper-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        fs::canonicalize(directory).unwrap()
+    }
+}
@@ -112,6 +112,34 @@ impl XContext {
     }
 }
 
+impl XContext {
+    /// RGBA-values of an X11-color-name. `None`, if the name is unknown.
+    pub fn rgba_of_color(&self, name: &str) -> Option<[u8; 4]> {
+        let mut xcolor: XColor = XColor {
+            pixel: 0,
+            red: 0,
+            green: 0,
+            blue: 0,
+            flags: 0,
+            pad: 0,
+        };
+
+        let name_cstr = CString::new(name).ok()?;
+        let cmap = unsafe { XDefaultColormap(self.display, self.screen) };
+
+        if unsafe { XParseColor(self.display, cmap, name_cstr.as_ptr(), &mut xcolor) } == 0 {
+            return None;
+        }
+
+        Some([
+            (xcolor.red / 256) as u8,
+            (xcolor.green / 256) as u8,
+            (xcolor.blue / 256) as u8,
+            255,
+        ])
+    }
+}
+
 impl Drop for XContext {
     fn drop(&mut self) {
         let options = self.options.clone();
@@ -263,6 +291,7 @@ mod tests {
     use std::ffi::CString;
     use std::os::raw::{c_uint, c_ulong};
     use std::sync::Arc;
+    use std::time::Duration;
 
     use x11::xlib::*;
 
@@ -271,6 +300,9 @@ mod tests {
     use super::EXIT_INVALID_WINDOW_ID;
     use super::EXIT_UNKOWN_COLOR;
 
+    use crate::playlist::PlayLength;
+    use crate::position::Alignment;
+    use crate::position::Offset;
     use crate::position::Scaling;
     use crate::position::ScalingFilter;
 
@@ -507,24 +539,70 @@ mod tests {
 
     fn create_options(window_id: &str) -> Arc<Options> {
         Arc::new(Options {
+            alignment: Alignment::CENTER,
             background_color: "#000000".to_string(),
+            background_fill: None,
+            background_image: None,
+            clear_cache: false,
+            crop: None,
             default_delay: 100,
+            downscaling_filter: None,
+            frame_delays: vec![],
+            linear_light: false,
+            memory_budget: None,
+            no_cache: false,
+            offset: Offset::default(),
             path_to_gif: "foo.gif".to_string(),
+            play_length: PlayLength::LOOPS(1),
+            playlist: vec![],
+            playlist_file: None,
             scaling: Scaling::FILL,
             scaling_filter: ScalingFilter::AUTO,
+            screen_wallpapers: vec![],
+            shuffle: false,
+            span: false,
+            target_size: None,
+            transition: None,
+            transition_duration: Duration::from_millis(1000),
+            transition_loops: false,
+            upscaling_filter: None,
             verbose: false,
+            watch: false,
             window_id: window_id.to_string(),
         })
     }
 
     fn create_option_with_color(color: &str) -> Arc<Options> {
         Arc::new(Options {
+            alignment: Alignment::CENTER,
             background_color: color.to_string(),
+            background_fill: None,
+            background_image: None,
+            clear_cache: false,
+            crop: None,
             default_delay: 100,
+            downscaling_filter: None,
+            frame_delays: vec![],
+            linear_light: false,
+            memory_budget: None,
+            no_cache: false,
+            offset: Offset::default(),
             path_to_gif: "foo.gif".to_string(),
+            play_length: PlayLength::LOOPS(1),
+            playlist: vec![],
+            playlist_file: None,
             scaling: Scaling::FILL,
             scaling_filter: ScalingFilter::AUTO,
+            screen_wallpapers: vec![],
+            shuffle: false,
+            span: false,
+            target_size: None,
+            transition: None,
+            transition_duration: Duration::from_millis(1000),
+            transition_loops: false,
+            upscaling_filter: None,
             verbose: false,
+            watch: false,
             window_id: "".to_string(),
         })
     }
@@ -24,6 +24,15 @@ run_debug $BACKGROUND $POSITION "sample-1x1.gif"
 run_debug $BACKGROUND $POSITION "sample-2x1.gif"
 run_debug $BACKGROUND $POSITION "sample-1x2.gif"
 run_debug $BACKGROUND $POSITION "sample-1x1-one-frame.gif"
+run_debug $BACKGROUND $POSITION "sample-disposal-background.gif"
+run_debug $BACKGROUND $POSITION "sample-disposal-keep.gif"
+run_debug $BACKGROUND $POSITION "sample-disposal-previous.gif"
+run_debug $BACKGROUND $POSITION "sample-animated.png"
+run_debug $BACKGROUND $POSITION "sample-animated.webp"
+run_debug $BACKGROUND $POSITION "sample-still.png"
+run_debug $BACKGROUND $POSITION "sample-still.jpg"
+run_debug $BACKGROUND $POSITION "sample-still.bmp"
+run_debug $BACKGROUND $POSITION "sequence"
 
 POSITION="MAX"
 run_debug $BACKGROUND $POSITION "sample-1x1.gif"